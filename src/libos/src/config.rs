@@ -103,6 +103,7 @@ pub struct Config {
     pub resource_limits: ConfigResourceLimits,
     pub process: ConfigProcess,
     pub env: ConfigEnv,
+    pub net: ConfigNet,
     pub app: Vec<ConfigApp>,
 }
 
@@ -124,6 +125,11 @@ pub struct ConfigEnv {
     pub untrusted: HashSet<String>,
 }
 
+#[derive(Debug)]
+pub struct ConfigNet {
+    pub sockopt_pass_through: bool,
+}
+
 #[derive(Clone, Debug)]
 pub struct ConfigMount {
     pub type_: ConfigMountFsType,
@@ -183,6 +189,7 @@ impl Config {
         let resource_limits = ConfigResourceLimits::from_input(&input.resource_limits)?;
         let process = ConfigProcess::from_input(&input.process)?;
         let env = ConfigEnv::from_input(&input.env)?;
+        let net = ConfigNet::from_input(&input.net)?;
 
         let app = {
             let mut app = Vec::new();
@@ -196,6 +203,7 @@ impl Config {
             resource_limits,
             process,
             env,
+            net,
             app,
         })
     }
@@ -240,6 +248,14 @@ impl ConfigEnv {
     }
 }
 
+impl ConfigNet {
+    fn from_input(input: &InputConfigNet) -> Result<ConfigNet> {
+        Ok(ConfigNet {
+            sockopt_pass_through: input.sockopt_pass_through,
+        })
+    }
+}
+
 impl ConfigApp {
     fn from_input(input: &InputConfigApp) -> Result<ConfigApp> {
         let stage = input.stage.clone();
@@ -363,6 +379,8 @@ struct InputConfig {
     #[serde(default)]
     pub env: InputConfigEnv,
     #[serde(default)]
+    pub net: InputConfigNet,
+    #[serde(default)]
     pub app: Vec<InputConfigApp>,
 }
 
@@ -437,6 +455,27 @@ impl Default for InputConfigEnv {
     }
 }
 
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct InputConfigNet {
+    #[serde(default = "InputConfigNet::get_sockopt_pass_through")]
+    pub sockopt_pass_through: bool,
+}
+
+impl InputConfigNet {
+    fn get_sockopt_pass_through() -> bool {
+        true
+    }
+}
+
+impl Default for InputConfigNet {
+    fn default() -> InputConfigNet {
+        InputConfigNet {
+            sockopt_pass_through: InputConfigNet::get_sockopt_pass_through(),
+        }
+    }
+}
+
 #[derive(Deserialize, Debug)]
 #[serde(deny_unknown_fields)]
 struct InputConfigMount {
@@ -25,6 +25,10 @@ impl<E: Event, F: EventFilter<E>> Notifier<E, F> {
     }
 
     /// Register an observer with its interesting events and metadata.
+    ///
+    /// Only a weak reference to the observer is kept, so the notifier does not
+    /// keep the observer alive. An observer that is freed without being
+    /// unregistered is removed at the next broadcast of its interesting events.
     pub fn register(
         &self,
         observer: Weak<dyn Observer<E>>,
@@ -47,21 +51,22 @@ impl<E: Event, F: EventFilter<E>> Notifier<E, F> {
 
     /// Broadcast an event to all registered observers.
     pub fn broadcast(&self, event: &E) {
-        let subscribers = self.subscribers.lock().unwrap();
-        for subscriber in subscribers.iter() {
+        let mut subscribers = self.subscribers.lock().unwrap();
+        subscribers.retain(|subscriber| {
             if let Some(filter) = subscriber.filter.as_ref() {
                 if !filter.filter(event) {
-                    continue;
+                    return true;
                 }
             }
             let observer = match subscriber.observer.upgrade() {
-                // TODO: should remove subscribers whose observers have been freed
-                None => return,
+                // The observer has been freed without unregistering itself
+                None => return false,
                 Some(observer) => observer,
             };
 
             observer.on_event(event, &subscriber.metadata);
-        }
+            true
+        });
     }
 }
 
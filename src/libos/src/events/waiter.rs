@@ -4,6 +4,7 @@ use std::time::Duration;
 
 use super::host_event_fd::HostEventFd;
 use crate::prelude::*;
+use crate::time::remaining_until;
 
 /// A waiter enables a thread to sleep.
 pub struct Waiter {
@@ -35,8 +36,20 @@ impl Waiter {
     /// After a `Waiter` being waken up, the `reset` method must be called so
     /// that the `Waiter` can use the `wait` or `wait_mut` methods to sleep the
     /// current thread again.
-    pub fn reset(&self) {
-        self.inner.reset();
+    ///
+    /// Returns whether a wakeup is discarded by the reset.
+    ///
+    /// A wakeup that comes right before the reset is discarded. To not miss
+    /// any wakeups, the waiter should be used with the following protocol:
+    /// 1. Reset the waiter (and make it visible to the wakers, e.g., enqueue it);
+    /// 2. Check the condition and stop if it is met;
+    /// 3. Wait and go back to step 1.
+    ///
+    /// Any wakeup discarded by step 1 is sent before the condition is checked in
+    /// step 2, so its effect is observed there. And any wakeup sent after step 1
+    /// stops the waiting in step 3.
+    pub fn reset(&self) -> bool {
+        self.inner.reset()
     }
 
     /// Put the current thread to sleep until being waken up by a waker.
@@ -60,6 +73,21 @@ impl Waiter {
         self.inner.wait_mut(timeout)
     }
 
+    /// Put the current thread to sleep until being waken up by a waker or
+    /// until the deadline.
+    ///
+    /// The `deadline` is an absolute time of the `CLOCK_MONOTONIC` clock. Unlike
+    /// calling `wait` repeatedly with a recomputed timeout, the remaining time
+    /// is always derived from the deadline, so no error is accumulated across
+    /// the repeated waits.
+    ///
+    /// The return values are the same as those of the `wait` method. If the
+    /// deadline has already passed and the waiter is not waken up, the method
+    /// fails with `ETIMEDOUT` immediately.
+    pub fn wait_deadline(&self, deadline: &Duration) -> Result<()> {
+        self.inner.wait_deadline(deadline)
+    }
+
     /// Create a waker that can wake up this waiter.
     ///
     /// `WaiterQueue` maintains a list of `Waker` internally to wake up the
@@ -95,41 +123,35 @@ impl Waker {
         }
     }
 
+    /// Returns whether this waker is created by the waiter.
+    pub fn is_waker_of(&self, waiter: &Waiter) -> bool {
+        self.inner.as_ptr() == Arc::as_ptr(&waiter.inner)
+    }
+
     /// Wake up waiters in batch, more efficient than waking up one-by-one.
-    pub fn batch_wake<'a, I: Iterator<Item = &'a Waker>>(iter: I) {
-        Inner::batch_wake(iter);
+    ///
+    /// The waiters that have been dropped or waken up already are skipped.
+    /// Returns the number of waiters that are actually waken up.
+    pub fn batch_wake<'a, I: Iterator<Item = &'a Waker>>(iter: I) -> usize {
+        Inner::batch_wake(iter)
     }
 }
 
-/// Instruction rearrangement about control dependency
-///
-/// Such as the following code:
-/// fn function(flag: bool, a: i32, b: i32) {
-///     if flag { // 1
-///         let i = a * b; // 2
-///     }
-/// }
-///
-/// Guidelines for compilation optimization：without changing the single-threaded semantics
-/// of the program, the execution order of statements can be rearranged. There is a control
-/// dependency between flag and i. When the instruction is reordered, step 2 will write the
-/// result value to the hardware cache, and when judged to be true, the result value will be
-/// written to the variable i. Therefore, controlling dependency does not prevent compiler
-/// optimizations
-///
 /// Note about memory ordering:
-/// Here is_woken needs to be synchronized with host_eventfd. The read operation of
-/// is_woken needs to see the change of the host_eventfd field. Just `Acquire` or
-/// `Release` needs to be used to make all the change of the host_eventfd visible to us.
 ///
-/// The ordering in CAS operations can be `Relaxed`, `Acquire`, `AcqRel` or `SeqCst`,
-/// The key is to consider the specific usage scenario. Here fail does not synchronize other
-/// variables in the CAS operation, which can use `Relaxed`, and the host_enent needs
-/// to be synchronized in success, so `Acquire` needs to be used so that we can see all the
-/// changes in the host_eventfd after that.
+/// The `is_woken` flag hands off the wakeup from the waker thread to the waiter
+/// thread. Whatever the waker has written before waking up the waiter (e.g., the
+/// data pushed into a channel) must be visible to the waiter once it observes
+/// `is_woken == true`. So the waker publishes the flag with `Release` in a
+/// successful CAS, and the waiter reads the flag with `Acquire`. `Relaxed` is
+/// not sufficient here: on weakly-ordered architectures, the waiter could see
+/// the flag set but a stale condition, go back to sleep and never be waken up
+/// again. A failed CAS means that some other waker has already done the job,
+/// so it publishes nothing and can use `Relaxed`.
 ///
-/// Although it is correct to use AcqRel, here I think it is okay to use Acquire, because
-/// you don't need to synchronize host_event before is_woken, only later.
+/// The host eventfd is only used to put the waiter thread to sleep and wake it
+/// up. After the waiter returns from polling the host eventfd, it always checks
+/// `is_woken` again, so a spurious or early return from the poll is harmless.
 struct Inner {
     is_woken: AtomicBool,
     host_eventfd: Arc<HostEventFd>,
@@ -149,8 +171,10 @@ impl Inner {
         self.is_woken.load(Ordering::Acquire)
     }
 
-    pub fn reset(&self) {
-        self.is_woken.store(false, Ordering::Release);
+    pub fn reset(&self) -> bool {
+        // Acquire the writes of a discarded waker, which the caller is expected
+        // to observe when checking its condition
+        self.is_woken.swap(false, Ordering::AcqRel)
     }
 
     pub fn wait(&self, timeout: Option<&Duration>) -> Result<()> {
@@ -160,6 +184,14 @@ impl Inner {
         Ok(())
     }
 
+    pub fn wait_deadline(&self, deadline: &Duration) -> Result<()> {
+        // The host keeps the remaining time up to date across the polls, so
+        // the clock is read only once. A zero remaining time makes the poll
+        // fail with ETIMEDOUT immediately.
+        let mut remain = Some(remaining_until(deadline)?);
+        self.do_wait_mut(&mut remain)
+    }
+
     pub fn wait_mut(&self, timeout: Option<&mut Duration>) -> Result<()> {
         let mut remain = timeout.as_ref().map(|d| **d);
 
@@ -183,20 +215,20 @@ impl Inner {
     pub fn wake(&self) {
         if self
             .is_woken
-            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .compare_exchange(false, true, Ordering::Release, Ordering::Relaxed)
             .is_ok()
         {
             self.host_eventfd.write_u64(1);
         }
     }
 
-    pub fn batch_wake<'a, I: Iterator<Item = &'a Waker>>(iter: I) {
+    pub fn batch_wake<'a, I: Iterator<Item = &'a Waker>>(iter: I) -> usize {
         let host_eventfds = iter
             .filter_map(|waker| waker.inner.upgrade())
             .filter(|inner| {
                 inner
                     .is_woken
-                    .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+                    .compare_exchange(false, true, Ordering::Release, Ordering::Relaxed)
                     .is_ok()
             })
             .map(|inner| inner.host_eventfd.host_fd())
@@ -204,6 +236,7 @@ impl Inner {
         unsafe {
             HostEventFd::write_u64_raw_and_batch(&host_eventfds, 1);
         }
+        host_eventfds.len()
     }
 
     pub fn host_eventfd(&self) -> &HostEventFd {
@@ -8,6 +8,10 @@ use crate::prelude::*;
 /// A queue for waiters.
 ///
 /// By using this queue, we can wake up threads in their waiters' enqueue order.
+/// The queue is FIFO-fair: a waiter is always waken up before the waiters that
+/// are enqueued after it, so it cannot be starved by a steady stream of later
+/// waiters. A waiter that is enqueued again, e.g., after a spurious wakeup or
+/// a timeout, goes to the back of the queue.
 ///
 /// While the queue is conceptually for `Waiter`s, it internally maintains a list
 /// of `Waker`s.
@@ -49,6 +53,10 @@ impl WaiterQueue {
 
     /// Reset a waiter and enqueue it.
     ///
+    /// This is the first step of the protocol described in `Waiter::reset`.
+    /// The caller must check its condition after this method and before
+    /// waiting.
+    ///
     /// It is allowed to enqueue a waiter more than once before it is dequeued.
     /// But this is usually not a good idea. It is the callers' responsibility
     /// to use the API properly.
@@ -60,34 +68,65 @@ impl WaiterQueue {
         wakers.push_back(waiter.waker());
     }
 
+    /// Dequeue a waiter without waking it up.
+    ///
+    /// A waiter that stops waiting before being waken up, e.g., due to a
+    /// timeout or an interrupt, should be dequeued by this method. Otherwise,
+    /// its waker stays in the queue until the next wakeup, and the queue grows
+    /// with each such wait if no wakeup ever comes.
+    ///
+    /// Returns whether the waiter was in the queue.
+    pub fn dequeue(&self, waiter: &Waiter) -> bool {
+        let mut wakers = self.wakers.lock().unwrap();
+        let old_len = wakers.len();
+        wakers.retain(|waker| !waker.is_waker_of(waiter));
+        let dequeued_count = old_len - wakers.len();
+        self.count.fetch_sub(dequeued_count, Ordering::Release);
+        dequeued_count > 0
+    }
+
     /// Dequeue a waiter and wake up its thread.
+    ///
+    /// Returns the number of waiters that are actually waken up, which is
+    /// zero if no waiter in the queue can be waken up.
     pub fn dequeue_and_wake_one(&self) -> usize {
         self.dequeue_and_wake_nr(1)
     }
 
     /// Dequeue all waiters and wake up their threads.
+    ///
+    /// This is a broadcast, like `FUTEX_WAKE` with `INT_MAX`. A waiter that is
+    /// being dropped concurrently or has been waken up by others is dequeued
+    /// but not counted, so no waiter is waken up twice.
     pub fn dequeue_and_wake_all(&self) -> usize {
         self.dequeue_and_wake_nr(usize::MAX)
     }
 
-    /// Deuque a maximum numer of waiters and wake up their threads.
+    /// Dequeue a maximum number of waiters and wake up their threads.
+    ///
+    /// Like `FUTEX_WAKE` with a bounded `nr_wake`, the waiters are waken up in
+    /// their enqueue order until `max_count` of them are actually waken up or
+    /// the queue becomes empty. A waiter that has been dropped or waken up by
+    /// others is dequeued but not counted, so it takes no wakeup from the
+    /// waiters behind it.
+    ///
+    /// Returns the number of waiters that are actually waken up.
     pub fn dequeue_and_wake_nr(&self, max_count: usize) -> usize {
-        // The quick path for a common case
-        if self.is_empty() {
-            return 0;
-        }
-
-        // Dequeue wakers
-        let to_wake = {
-            let mut wakers = self.wakers.lock().unwrap();
-            let max_count = max_count.min(wakers.len());
-            let to_wake: Vec<Waker> = wakers.drain(..max_count).collect();
-            self.count.fetch_sub(to_wake.len(), Ordering::Release);
-            to_wake
-        };
+        let mut woken_count = 0;
+        // The quick path for a common case is the emptiness check
+        while woken_count < max_count && !self.is_empty() {
+            // Dequeue wakers
+            let to_wake = {
+                let mut wakers = self.wakers.lock().unwrap();
+                let nr_wakers = (max_count - woken_count).min(wakers.len());
+                let to_wake: Vec<Waker> = wakers.drain(..nr_wakers).collect();
+                self.count.fetch_sub(to_wake.len(), Ordering::Release);
+                to_wake
+            };
 
-        // Wake in batch
-        Waker::batch_wake(to_wake.iter());
-        to_wake.len()
+            // Wake in batch
+            woken_count += Waker::batch_wake(to_wake.iter());
+        }
+        woken_count
     }
 }
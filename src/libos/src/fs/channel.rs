@@ -1,11 +1,13 @@
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Weak;
+use std::time::Duration;
 
 use ringbuf::{Consumer as RbConsumer, Producer as RbProducer, RingBuffer};
 
 use super::{IoEvents, IoNotifier};
 use crate::events::{Event, EventFilter, Notifier, Observer, Waiter, WaiterQueueObserver};
 use crate::prelude::*;
+use crate::time::deadline_after;
 
 /// A unidirectional communication channel, intended to implement IPC, e.g., pipe,
 /// unix domain sockets, etc.
@@ -31,7 +33,11 @@ impl<I> Channel<I> {
         producer.peer_notifier = Arc::downgrade(&consumer.notifier);
         consumer.peer_notifier = Arc::downgrade(&producer.notifier);
         // An endpoint registers itself as an observer to its own notifier so
-        // that it can be waken up by its peer.
+        // that it can be waken up by its peer. A blocked producer is only
+        // waken up when its consumer frees up space (IoEvents::OUT) or is shut
+        // down (IoEvents::ERR), so it never retries without a chance to make
+        // progress. It is notified of every pop, not just the one that makes
+        // the channel not full, as a message may need more space than that.
         producer.notifier.register(
             Arc::downgrade(&producer.observer) as Weak<dyn Observer<_>>,
             None,
@@ -130,6 +136,9 @@ macro_rules! impl_end_point_type {
             notifier: Arc<IoNotifier>,
             peer_notifier: Weak<IoNotifier>,
             is_nonblocking: AtomicBool,
+            timeout: SgxMutex<Option<Duration>>,
+            // The number of items ever pushed or popped, updated with the inner locked
+            offset: AtomicUsize,
         }
 
         impl<$i> $end_point<$i> {
@@ -139,6 +148,8 @@ macro_rules! impl_end_point_type {
                 let notifier = Arc::new(IoNotifier::new());
                 let peer_notifier = Default::default();
                 let is_nonblocking = AtomicBool::new(false);
+                let timeout = SgxMutex::new(None);
+                let offset = AtomicUsize::new(0);
                 Self {
                     inner,
                     state,
@@ -146,6 +157,8 @@ macro_rules! impl_end_point_type {
                     notifier,
                     peer_notifier,
                     is_nonblocking,
+                    timeout,
+                    offset,
                 }
             }
 
@@ -174,6 +187,38 @@ macro_rules! impl_end_point_type {
                 }
             }
 
+            /// Returns the timeout of blocking operations on the endpoint.
+            ///
+            /// By default, there is no timeout.
+            pub fn timeout(&self) -> Option<Duration> {
+                *self.timeout.lock().unwrap()
+            }
+
+            /// Set the timeout of blocking operations on the endpoint.
+            ///
+            /// A blocking operation fails with `EAGAIN` if it cannot be done
+            /// before the timeout.
+            pub fn set_timeout(&self, timeout: Option<Duration>) {
+                *self.timeout.lock().unwrap() = timeout;
+            }
+
+            /// Returns the offset of the next item in the stream of all the items
+            /// ever passed through the channel.
+            ///
+            /// For a producer, this is the number of the items pushed so far. For
+            /// a consumer, this is the number of the items popped so far. So the
+            /// items before the offset of the consumer have been consumed.
+            pub fn offset(&self) -> usize {
+                self.offset.load(Ordering::Acquire)
+            }
+
+            // Advance the offset by the number of the items pushed or popped. This
+            // must be called with the inner locked, so that the offset of the items
+            // matches their order in the channel.
+            fn advance_offset(&self, count: usize) -> usize {
+                self.offset.fetch_add(count, Ordering::AcqRel)
+            }
+
             fn trigger_peer_events(&self, events: &IoEvents) {
                 if let Some(peer_notifier) = self.peer_notifier.upgrade() {
                     peer_notifier.broadcast(events);
@@ -185,9 +230,12 @@ macro_rules! impl_end_point_type {
 
 // Just like a normal loop, except that a waiter queue (as well as a waiter)
 // is used to avoid busy loop. This macro is used in the push/pop implementation
-// below.
+// below. The loop fails with EAGAIN once the timeout, if any, is up. The timeout
+// is turned into a deadline at the start, so the time spent in the loop body
+// counts towards it. The waiter is dequeued when the loop fails so that no
+// stale waker is left behind.
 macro_rules! waiter_loop {
-    ($loop_body: block, $waiter_queue: expr) => {
+    ($loop_body: block, $waiter_queue: expr, $timeout: expr) => {
         // Try without creating a waiter. This saves some CPU cycles if the
         // first attempt succeeds.
         {
@@ -197,6 +245,10 @@ macro_rules! waiter_loop {
         // The main loop
         let waiter = Waiter::new();
         let waiter_queue = $waiter_queue;
+        let deadline = match $timeout {
+            Some(timeout) => Some(deadline_after(&timeout)?),
+            None => None,
+        };
         loop {
             waiter_queue.reset_and_enqueue(&waiter);
 
@@ -204,7 +256,17 @@ macro_rules! waiter_loop {
                 $loop_body
             }
 
-            waiter.wait(None)?;
+            let ret = match deadline.as_ref() {
+                Some(deadline) => waiter.wait_deadline(deadline),
+                None => waiter.wait(None),
+            };
+            if let Err(e) = ret {
+                waiter_queue.dequeue(&waiter);
+                if e.errno() == ETIMEDOUT {
+                    return_errno!(EAGAIN, "timed out");
+                }
+                return Err(e);
+            }
         }
     };
 }
@@ -227,6 +289,7 @@ impl<I> Producer<I> {
 
                 item = match rb_producer.push(item) {
                     Ok(()) => {
+                        self.advance_offset(1);
                         drop(rb_producer);
                         self.trigger_peer_events(&IoEvents::IN);
                         return Ok(());
@@ -238,7 +301,8 @@ impl<I> Producer<I> {
                     return_errno!(EAGAIN, "try again later");
                 }
             },
-            self.observer.waiter_queue()
+            self.observer.waiter_queue(),
+            self.timeout()
         );
     }
 
@@ -283,6 +347,50 @@ impl<I> Producer<I> {
     pub fn is_peer_shutdown(&self) -> bool {
         self.state.is_consumer_shutdown()
     }
+
+    /// Get the number of items pushed but not consumed yet.
+    ///
+    /// The items can never be consumed once the consumer is shut down.
+    pub fn items_to_consume(&self) -> usize {
+        if self.is_peer_shutdown() {
+            0
+        } else {
+            self.inner.lock().unwrap().len()
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        let rb_producer = self.inner.lock().unwrap();
+        rb_producer.capacity()
+    }
+
+    /// Change the capacity of the channel, given the consumer of the channel.
+    ///
+    /// The items in the channel are kept, so the capacity is no less than the
+    /// number of the items. Threads blocked on pushing are waken up to make use
+    /// of the new capacity.
+    pub fn resize(&self, consumer: &Consumer<I>, capacity: usize) -> Result<()> {
+        if !Arc::ptr_eq(&self.state, &consumer.state) {
+            return_errno!(EINVAL, "the consumer is not of the same channel");
+        }
+
+        {
+            // Always lock the producer before the consumer
+            let mut rb_producer = self.inner.lock().unwrap();
+            let mut rb_consumer = consumer.inner.lock().unwrap();
+            let capacity = capacity.max(rb_consumer.len());
+            let (mut new_producer, new_consumer) = RingBuffer::new(capacity).split();
+            while let Some(item) = rb_consumer.pop() {
+                // Never fails as the capacity is enough for all the items
+                let _ = new_producer.push(item);
+            }
+            *rb_producer = new_producer;
+            *rb_consumer = new_consumer;
+        }
+
+        consumer.trigger_peer_events(&IoEvents::OUT);
+        Ok(())
+    }
 }
 
 impl<I: Copy> Producer<I> {
@@ -291,9 +399,29 @@ impl<I: Copy> Producer<I> {
     }
 
     pub fn push_slices(&self, item_slices: &[&[I]]) -> Result<usize> {
+        self.push_slices_with_offset(item_slices, false)
+            .map(|(_, count)| count)
+    }
+
+    /// Push the items without blocking, regardless of the nonblocking mode.
+    pub fn try_push_slices(&self, item_slices: &[&[I]]) -> Result<usize> {
+        self.push_slices_with_offset(item_slices, true)
+            .map(|(_, count)| count)
+    }
+
+    /// Push the items, and return the offset of the first pushed item along
+    /// with the number of the pushed items.
+    ///
+    /// The items never block if `dontwait` is true, regardless of the
+    /// nonblocking mode. See `offset` for the meaning of the offset.
+    pub fn push_slices_with_offset(
+        &self,
+        item_slices: &[&[I]],
+        dontwait: bool,
+    ) -> Result<(usize, usize)> {
         let len: usize = item_slices.iter().map(|slice| slice.len()).sum();
         if len == 0 {
-            return Ok(0);
+            return Ok((self.offset(), 0));
         }
 
         waiter_loop!(
@@ -315,16 +443,76 @@ impl<I: Copy> Producer<I> {
                 }
 
                 if total_count > 0 {
+                    let offset = self.advance_offset(total_count);
                     drop(rb_producer);
                     self.trigger_peer_events(&IoEvents::IN);
-                    return Ok(total_count);
+                    return Ok((offset, total_count));
                 }
 
-                if self.is_nonblocking() {
+                if dontwait || self.is_nonblocking() {
+                    return_errno!(EAGAIN, "try again later");
+                }
+            },
+            self.observer.waiter_queue(),
+            self.timeout()
+        );
+    }
+}
+
+// The length of the header that records the length of a message
+const MSG_HEADER_LEN: usize = std::mem::size_of::<u32>();
+
+impl Producer<u8> {
+    /// Push the slices as a single message.
+    ///
+    /// A message is pushed as a whole or not at all, and is popped as a whole
+    /// by `Consumer::pop_msg`. Fails with `EMSGSIZE` if the message can never
+    /// fit in the channel.
+    pub fn push_msg(&self, item_slices: &[&[u8]], dontwait: bool) -> Result<usize> {
+        self.push_msg_with_offset(item_slices, dontwait)
+            .map(|(_, len)| len)
+    }
+
+    /// Push the slices as a single message, and return the offset of the
+    /// message along with its length.
+    ///
+    /// The offset counts the header of each message. See `offset` for the
+    /// meaning of the offset.
+    pub fn push_msg_with_offset(
+        &self,
+        item_slices: &[&[u8]],
+        dontwait: bool,
+    ) -> Result<(usize, usize)> {
+        let len: usize = item_slices.iter().map(|slice| slice.len()).sum();
+        if MSG_HEADER_LEN + len > self.capacity() {
+            return_errno!(EMSGSIZE, "the message is too long");
+        }
+        let header = (len as u32).to_ne_bytes();
+
+        waiter_loop!(
+            {
+                let mut rb_producer = self.inner.lock().unwrap();
+                if self.is_self_shutdown() || self.is_peer_shutdown() {
+                    return_errno!(EPIPE, "one or both endpoints have been shutdown");
+                }
+
+                if rb_producer.capacity() - rb_producer.len() >= MSG_HEADER_LEN + len {
+                    rb_producer.push_slice(&header);
+                    for items in item_slices {
+                        rb_producer.push_slice(items);
+                    }
+                    let offset = self.advance_offset(MSG_HEADER_LEN + len);
+                    drop(rb_producer);
+                    self.trigger_peer_events(&IoEvents::IN);
+                    return Ok((offset, len));
+                }
+
+                if dontwait || self.is_nonblocking() {
                     return_errno!(EAGAIN, "try again later");
                 }
             },
-            self.observer.waiter_queue()
+            self.observer.waiter_queue(),
+            self.timeout()
         );
     }
 }
@@ -352,6 +540,7 @@ impl<I> Consumer<I> {
                 }
 
                 if let Some(item) = rb_consumer.pop() {
+                    self.advance_offset(1);
                     drop(rb_consumer);
                     self.trigger_peer_events(&IoEvents::OUT);
                     return Ok(Some(item));
@@ -364,7 +553,8 @@ impl<I> Consumer<I> {
                     return_errno!(EAGAIN, "try again later");
                 }
             },
-            self.observer.waiter_queue()
+            self.observer.waiter_queue(),
+            self.timeout()
         );
     }
 
@@ -436,6 +626,15 @@ impl<I: Copy> Consumer<I> {
     }
 
     pub fn pop_slices(&self, item_slices: &mut [&mut [I]]) -> Result<usize> {
+        self.do_pop_slices(item_slices, false)
+    }
+
+    /// Pop the items without blocking, regardless of the nonblocking mode.
+    pub fn try_pop_slices(&self, item_slices: &mut [&mut [I]]) -> Result<usize> {
+        self.do_pop_slices(item_slices, true)
+    }
+
+    fn do_pop_slices(&self, item_slices: &mut [&mut [I]], dontwait: bool) -> Result<usize> {
         let len: usize = item_slices.iter().map(|slice| slice.len()).sum();
         if len == 0 {
             return Ok(0);
@@ -460,6 +659,7 @@ impl<I: Copy> Consumer<I> {
                 }
 
                 if total_count > 0 {
+                    self.advance_offset(total_count);
                     drop(rb_consumer);
                     self.trigger_peer_events(&IoEvents::OUT);
                     return Ok(total_count);
@@ -468,11 +668,136 @@ impl<I: Copy> Consumer<I> {
                 if self.is_peer_shutdown() {
                     return Ok(0);
                 }
-                if self.is_nonblocking() {
+                if dontwait || self.is_nonblocking() {
+                    return_errno!(EAGAIN, "try again later");
+                }
+            },
+            self.observer.waiter_queue(),
+            self.timeout()
+        );
+    }
+}
+
+impl<I: Copy> Consumer<I> {
+    /// Copy items into the slices without removing them from the channel.
+    ///
+    /// The semantic of this method is the same as `pop_slices` except that
+    /// the items in the channel are not consumed.
+    pub fn peek_slices(&self, item_slices: &mut [&mut [I]]) -> Result<usize> {
+        self.do_peek_slices(item_slices, false)
+    }
+
+    /// Peek the items without blocking, regardless of the nonblocking mode.
+    pub fn try_peek_slices(&self, item_slices: &mut [&mut [I]]) -> Result<usize> {
+        self.do_peek_slices(item_slices, true)
+    }
+
+    fn do_peek_slices(&self, item_slices: &mut [&mut [I]], dontwait: bool) -> Result<usize> {
+        let len: usize = item_slices.iter().map(|slice| slice.len()).sum();
+        if len == 0 {
+            return Ok(0);
+        }
+
+        waiter_loop!(
+            {
+                let rb_consumer = self.inner.lock().unwrap();
+                if self.is_self_shutdown() {
+                    return_errno!(EPIPE, "this endpoint has been shutdown");
+                }
+
+                let mut total_count = 0;
+                rb_consumer.access(|first, second| {
+                    let mut src_iter = first.iter().chain(second.iter());
+                    for dst in item_slices.iter_mut().flat_map(|items| items.iter_mut()) {
+                        match src_iter.next() {
+                            Some(src) => *dst = *src,
+                            None => break,
+                        }
+                        total_count += 1;
+                    }
+                });
+
+                if total_count > 0 {
+                    return Ok(total_count);
+                }
+
+                if self.is_peer_shutdown() {
+                    return Ok(0);
+                }
+                if dontwait || self.is_nonblocking() {
+                    return_errno!(EAGAIN, "try again later");
+                }
+            },
+            self.observer.waiter_queue(),
+            self.timeout()
+        );
+    }
+}
+
+impl Consumer<u8> {
+    /// Pop a single message pushed by `Producer::push_msg`.
+    ///
+    /// The part of the message that does not fit in the slices is discarded,
+    /// unless `peek` is true, in which case the message is left in the
+    /// channel. Returns the number of bytes copied and the length of the
+    /// message.
+    pub fn pop_msg(
+        &self,
+        item_slices: &mut [&mut [u8]],
+        peek: bool,
+        dontwait: bool,
+    ) -> Result<(usize, usize)> {
+        waiter_loop!(
+            {
+                let mut rb_consumer = self.inner.lock().unwrap();
+                if self.is_self_shutdown() {
+                    return_errno!(EPIPE, "this endpoint has been shutdown");
+                }
+
+                if !rb_consumer.is_empty() {
+                    let mut msg_len = 0;
+                    let mut copy_len = 0;
+                    rb_consumer.access(|first, second| {
+                        let mut src_iter = first.iter().chain(second.iter());
+                        let mut header = [0; MSG_HEADER_LEN];
+                        for byte in header.iter_mut() {
+                            *byte = *src_iter.next().unwrap();
+                        }
+                        msg_len = u32::from_ne_bytes(header) as usize;
+
+                        let mut msg_iter = src_iter.take(msg_len);
+                        for dst in item_slices.iter_mut().flat_map(|items| items.iter_mut()) {
+                            match msg_iter.next() {
+                                Some(src) => *dst = *src,
+                                None => break,
+                            }
+                            copy_len += 1;
+                        }
+                    });
+
+                    if !peek {
+                        let mut discard_buf = [0; 256];
+                        let mut discard_len = MSG_HEADER_LEN + msg_len;
+                        while discard_len > 0 {
+                            let len = discard_len.min(discard_buf.len());
+                            discard_len -= rb_consumer.pop_slice(&mut discard_buf[..len]);
+                        }
+                        self.advance_offset(MSG_HEADER_LEN + msg_len);
+                        drop(rb_consumer);
+                        self.trigger_peer_events(&IoEvents::OUT);
+                    }
+                    return Ok((copy_len, msg_len));
+                }
+
+                if self.is_peer_shutdown() {
+                    return Ok((0, 0));
+                }
+                if dontwait || self.is_nonblocking() {
                     return_errno!(EAGAIN, "try again later");
                 }
             },
-            self.observer.waiter_queue()
+            self.observer.waiter_queue(),
+            self.timeout()
         );
     }
 }
@@ -14,6 +14,10 @@ macro_rules! return_op_unsupported_error {
     }};
 }
 
+/// The maximum length of the temporary buffer of the default `readv` and
+/// `writev`, which is allocated from the LibOS heap.
+const MAX_VEC_IO_LEN: usize = 128 * 1024;
+
 pub trait File: Debug + Sync + Send + Any {
     fn read(&self, buf: &mut [u8]) -> Result<usize> {
         return_op_unsupported_error!("read")
@@ -31,16 +35,96 @@ pub trait File: Debug + Sync + Send + Any {
         return_op_unsupported_error!("write_at")
     }
 
+    /// Read into multiple buffers.
+    ///
+    /// By default, the data is read by calls to `read` and then filled into
+    /// the buffers in order. So the semantics of `read`, e.g., reading a whole
+    /// message at once, are kept. The temporary buffer is capped at
+    /// `MAX_VEC_IO_LEN` bytes, so more data is read by further calls, until a
+    /// call reads less than asked for. This is what Linux does for files
+    /// without vectored I/O.
     fn readv(&self, bufs: &mut [&mut [u8]]) -> Result<usize> {
-        return_op_unsupported_error!("readv")
+        let total_len: usize = bufs.iter().map(|buf| buf.len()).sum();
+        let mut total_buf = vec![0; total_len.min(MAX_VEC_IO_LEN)];
+        let mut bufs = bufs.iter_mut().filter(|buf| !buf.is_empty());
+        let mut cur_buf = bufs.next();
+        let mut cur_offset = 0;
+        let mut read_len = 0;
+        while read_len < total_len {
+            let chunk_len = (total_len - read_len).min(MAX_VEC_IO_LEN);
+            let len = match self.read(&mut total_buf[..chunk_len]) {
+                Ok(len) => len,
+                Err(_) if read_len > 0 => break,
+                Err(e) => return Err(e),
+            };
+
+            let mut remain = &total_buf[..len];
+            while let Some(buf) = cur_buf.as_mut() {
+                if remain.is_empty() {
+                    break;
+                }
+                let copy_len = (buf.len() - cur_offset).min(remain.len());
+                buf[cur_offset..cur_offset + copy_len].copy_from_slice(&remain[..copy_len]);
+                remain = &remain[copy_len..];
+                cur_offset += copy_len;
+                if cur_offset == buf.len() {
+                    cur_buf = bufs.next();
+                    cur_offset = 0;
+                }
+            }
+            read_len += len;
+            if len < chunk_len {
+                break;
+            }
+        }
+        Ok(read_len)
     }
 
+    /// Write from multiple buffers.
+    ///
+    /// By default, the buffers are gathered and then written by calls to
+    /// `write`. At most `MAX_VEC_IO_LEN` bytes are gathered at a time, so the
+    /// rest of the buffers are written by further calls, until a call writes
+    /// less than asked for.
     fn writev(&self, bufs: &[&[u8]]) -> Result<usize> {
-        return_op_unsupported_error!("writev")
+        let total_len: usize = bufs.iter().map(|buf| buf.len()).sum();
+        let mut total_buf = Vec::with_capacity(total_len.min(MAX_VEC_IO_LEN));
+        let mut bufs = bufs.iter().filter(|buf| !buf.is_empty());
+        let mut remain: &[u8] = &[];
+        let mut written_len = 0;
+        while written_len < total_len {
+            total_buf.clear();
+            while total_buf.len() < MAX_VEC_IO_LEN {
+                if remain.is_empty() {
+                    match bufs.next() {
+                        Some(buf) => remain = *buf,
+                        None => break,
+                    }
+                }
+                let len = remain.len().min(MAX_VEC_IO_LEN - total_buf.len());
+                total_buf.extend_from_slice(&remain[..len]);
+                remain = &remain[len..];
+            }
+
+            let len = match self.write(&total_buf) {
+                Ok(len) => len,
+                Err(_) if written_len > 0 => break,
+                Err(e) => return Err(e),
+            };
+            written_len += len;
+            if len < total_buf.len() {
+                break;
+            }
+        }
+        Ok(written_len)
     }
 
+    /// Reposition the offset of the file.
+    ///
+    /// Like Linux, a file that is not seekable, e.g., a socket, fails with
+    /// `ESPIPE`.
     fn seek(&self, pos: SeekFrom) -> Result<off_t> {
-        return_op_unsupported_error!("seek")
+        return_op_unsupported_error!("seek", ESPIPE)
     }
 
     fn preadv(&self, bufs: &mut [&mut [u8]], offset: usize) -> Result<usize> {
@@ -51,8 +135,9 @@ pub trait File: Debug + Sync + Send + Any {
         return_op_unsupported_error!("pwritev")
     }
 
+    /// Returns the current offset of the file.
     fn position(&self) -> Result<off_t> {
-        return_op_unsupported_error!("position")
+        self.seek(SeekFrom::Current(0))
     }
 
     fn metadata(&self) -> Result<Metadata> {
@@ -79,8 +164,12 @@ pub trait File: Debug + Sync + Send + Any {
         Ok(())
     }
 
+    /// Perform a device-specific operation on the file.
+    ///
+    /// The generic commands, e.g., `FIOCLEX`, are handled before reaching the
+    /// file. Like Linux, a file that supports no commands fails with `ENOTTY`.
     fn ioctl(&self, cmd: &mut IoctlCmd) -> Result<i32> {
-        return_op_unsupported_error!("ioctl")
+        return_op_unsupported_error!("ioctl", ENOTTY)
     }
 
     fn access_mode(&self) -> Result<AccessMode> {
@@ -29,6 +29,8 @@ impl_ioctl_nums_and_cmds! {
     // Get terminal attributes
     TCGETS => (0x5401, mut KernelTermios), // ignore
     TCSETS => (0x5402, KernelTermios),
+    // Get the number of bytes in the output buffer, which is also SIOCOUTQ for sockets
+    TIOCOUTQ => (0x5411, mut i32),
     // Get window size
     TIOCGWINSZ => (0x5413, mut WinSize),
     // Set window size
@@ -40,12 +42,14 @@ impl_ioctl_nums_and_cmds! {
     // the foreground process group and all processes in the current session lose their controlling
     // terminal
     TIOCNOTTY => (0x5422, ()),
-    // Get the number of bytes in the input buffer
+    // Get the number of bytes in the input buffer, which is also SIOCINQ for sockets
     FIONREAD => (0x541B, mut i32),
     // Don't close on exec
     FIONCLEX => (0x5450, ()),
     // Set close on exec
     FIOCLEX => (0x5451, ()),
+    // Check whether the socket is at the out-of-band mark
+    SIOCATMARK => (0x8905, mut i32),
     // Low-level access to Linux network devices on man7/netdevice.7
     // Only non-privileged operations are supported for now
     SIOCGIFNAME => (0x8910, mut IfReq),
@@ -80,11 +84,24 @@ impl<'a> IoctlCmd<'a> {
                     );
                 }
             }
-            IoctlCmd::FIONREAD(nread_ref) => {
+            IoctlCmd::FIONREAD(nread_ref) | IoctlCmd::TIOCOUTQ(nread_ref) => {
                 if (**nread_ref < 0) {
                     return_errno!(EINVAL, "invalid data from host");
                 }
             }
+            IoctlCmd::SIOCATMARK(atmark_ref) => {
+                if **atmark_ref != 0 && **atmark_ref != 1 {
+                    return_errno!(EINVAL, "invalid data from host");
+                }
+            }
+            IoctlCmd::SIOCGIFADDR(ifreq_ref) => {
+                // Only IPv4 addresses are returned by SIOCGIFADDR
+                let ifr_union = &ifreq_ref.ifr_union;
+                let family = u16::from_ne_bytes([ifr_union[0], ifr_union[1]]);
+                if family != libc::AF_INET as u16 {
+                    return_errno!(EINVAL, "invalid data from host");
+                }
+            }
             _ => {}
         }
 
@@ -66,26 +66,29 @@ impl FileTable {
     }
 
     pub fn put(&mut self, file: FileRef, close_on_spawn: bool) -> FileDesc {
+        let min_free_fd = self.min_free_fd() as usize;
         let mut table = &mut self.table;
-
-        let min_free_fd = if self.num_fds < table.len() {
-            table
-                .iter()
-                .enumerate()
-                .find(|&(idx, opt)| opt.is_none())
-                .unwrap()
-                .0
-        } else {
+        if min_free_fd == table.len() {
             table.push(None);
-            table.len() - 1
-        };
+        }
 
-        table[min_free_fd as usize] = Some(FileTableEntry::new(file, close_on_spawn));
+        table[min_free_fd] = Some(FileTableEntry::new(file, close_on_spawn));
         self.num_fds += 1;
 
         min_free_fd as FileDesc
     }
 
+    /// Returns the lowest fd that is not in use, which is the fd to be
+    /// allocated by the next `put`.
+    pub fn min_free_fd(&self) -> FileDesc {
+        let table = &self.table;
+        if self.num_fds < table.len() {
+            table.iter().position(|opt| opt.is_none()).unwrap() as FileDesc
+        } else {
+            table.len() as FileDesc
+        }
+    }
+
     pub fn put_at(&mut self, fd: FileDesc, file: FileRef, close_on_spawn: bool) -> Option<FileRef> {
         let mut table = &mut self.table;
         let mut table_entry = Some(FileTableEntry::new(file, close_on_spawn));
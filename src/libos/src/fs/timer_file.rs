@@ -94,6 +94,45 @@ extern "C" {
     ) -> sgx_status_t;
 }
 
+/// Ioctl to set the number of expirations of the timer, i.e., TFD_IOC_SET_TICKS
+const TFD_IOC_SET_TICKS: u32 =
+    StructuredIoctlNum::new::<u64>(0, b'T', StructuredIoctlArgType::Input).as_u32();
+
+impl TimerFile {
+    fn ioctl_impl(&self, cmd: &mut IoctlCmd) -> Result<i32> {
+        match cmd {
+            IoctlCmd::FIONBIO(nonblocking) => {
+                // Go through the status flags so that FIONBIO and fcntl(F_SETFL)
+                // are consistent with each other
+                let mut status_flags = self.status_flags()?;
+                status_flags.set(StatusFlags::O_NONBLOCK, **nonblocking != 0);
+                self.set_status_flags(status_flags)?;
+                return Ok(0);
+            }
+            IoctlCmd::NonBuiltin(nonbuiltin_cmd)
+                if nonbuiltin_cmd.cmd_num().as_u32() == TFD_IOC_SET_TICKS => {}
+            _ => return_errno!(ENOTTY, "not a timerfd ioctl"),
+        }
+
+        let cmd_num = cmd.cmd_num() as c_int;
+        let cmd_arg_ptr = cmd.arg_ptr() as *mut c_void;
+        let ret = try_libc!({
+            let mut retval: i32 = 0;
+            let status = occlum_ocall_ioctl(
+                &mut retval as *mut i32,
+                self.host_fd(),
+                cmd_num,
+                cmd_arg_ptr,
+                cmd.arg_len(),
+            );
+            assert!(status == sgx_status_t::SGX_SUCCESS);
+            retval
+        });
+        cmd.validate_arg_and_ret_vals(ret)?;
+        Ok(ret)
+    }
+}
+
 extern "C" {
     fn occlum_ocall_timerfd_gettime(
         ret: *mut i32,
@@ -115,10 +154,9 @@ impl File for TimerFile {
         Ok(ret)
     }
 
-    // TODO: implement ioctl
-    // fn ioctl(&self, cmd: &mut IoctlCmd) -> Result<i32> {
-    //     self.ioctl_impl(cmd)
-    // }
+    fn ioctl(&self, cmd: &mut IoctlCmd) -> Result<i32> {
+        self.ioctl_impl(cmd)
+    }
 
     fn access_mode(&self) -> Result<AccessMode> {
         Ok(AccessMode::O_RDWR)
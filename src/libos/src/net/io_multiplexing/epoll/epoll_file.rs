@@ -213,6 +213,8 @@ impl EpollFile {
 
             // If any results, we can return
             if count > 0 {
+                self.waiters.dequeue(waiter.as_ref());
+
                 // Push the entries that are still ready after polling back to the ready list
                 if reinsert.len() > 0 {
                     self.push_ready_iter(reinsert.into_iter());
@@ -224,6 +226,7 @@ impl EpollFile {
             // Wait for a while to try again later.
             let ret = waiter.wait_mut(timeout.as_mut());
             if let Err(e) = ret {
+                self.waiters.dequeue(waiter.as_ref());
                 if e.errno() == ETIMEDOUT {
                     return Ok(0);
                 } else {
@@ -49,6 +49,20 @@ pub fn do_poll_new(poll_fds: &[PollFd], mut timeout: Option<&mut Duration>) -> R
 
     debug_assert!(files_and_expected_events.len() == poll_fds.len());
 
+    // A zero timeout only takes a snapshot of the readiness. Unless the states
+    // of host files have to be refreshed by polling the host, this needs no
+    // monitor, which saves registering observers and creating a waiter.
+    let is_zero_timeout = timeout
+        .as_ref()
+        .map_or(false, |timeout| **timeout == Duration::from_secs(0));
+    let has_host_files = files_and_expected_events
+        .iter()
+        .flatten()
+        .any(|(file, _)| file.host_fd().is_some());
+    if is_zero_timeout && !has_host_files {
+        return Ok(poll_files_once(&files_and_expected_events, poll_fds));
+    }
+
     // Now that all fds are valid, we set up a monitor for the set of files
     let mut monitor = {
         let expected_num_files = files_and_expected_events.len();
@@ -66,23 +80,7 @@ pub fn do_poll_new(poll_fds: &[PollFd], mut timeout: Option<&mut Duration>) -> R
     loop {
         monitor.reset_events();
 
-        // Poll each and every interesting file
-        let mut count = 0;
-        for (file_and_event, poll_fd) in files_and_expected_events.iter().zip(poll_fds.iter()) {
-            // Ignore negative poll_fds
-            if file_and_event.is_none() {
-                continue;
-            }
-            let mask = poll_fd.events;
-            let file = &file_and_event.as_ref().unwrap().0;
-            let events = file.poll_new() & mask;
-            if !events.is_empty() {
-                poll_fd.revents.set(events);
-                debug!("poll fd = {:?}, revents = {:?}", poll_fd, events);
-                count += 1;
-            }
-        }
-
+        let count = poll_files_once(&files_and_expected_events, poll_fds);
         if count > 0 {
             return Ok(count);
         }
@@ -105,6 +103,31 @@ pub fn do_poll_new(poll_fds: &[PollFd], mut timeout: Option<&mut Duration>) -> R
     }
 }
 
+/// Poll each and every interesting file once and set the ready events.
+///
+/// Returns the number of files with any ready events.
+fn poll_files_once(
+    files_and_expected_events: &[Option<(FileRef, IoEvents)>],
+    poll_fds: &[PollFd],
+) -> usize {
+    let mut count = 0;
+    for (file_and_event, poll_fd) in files_and_expected_events.iter().zip(poll_fds.iter()) {
+        // Ignore negative poll_fds
+        if file_and_event.is_none() {
+            continue;
+        }
+        let mask = poll_fd.events;
+        let file = &file_and_event.as_ref().unwrap().0;
+        let events = file.poll_new() & mask;
+        if !events.is_empty() {
+            poll_fd.revents.set(events);
+            debug!("poll fd = {:?}, revents = {:?}", poll_fd, events);
+            count += 1;
+        }
+    }
+    count
+}
+
 #[derive(Debug)]
 pub struct PollFd {
     fd: FileDesc,
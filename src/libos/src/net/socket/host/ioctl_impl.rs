@@ -3,8 +3,24 @@ use fs::{occlum_ocall_ioctl, BuiltinIoctlNum, IfConf, IoctlCmd};
 
 impl HostSocket {
     pub(super) fn ioctl_impl(&self, cmd: &mut IoctlCmd) -> Result<i32> {
-        if let IoctlCmd::SIOCGIFCONF(arg_ref) = cmd {
-            return self.ioctl_getifconf(arg_ref);
+        match cmd {
+            IoctlCmd::SIOCGIFCONF(arg_ref) => {
+                return self.ioctl_getifconf(arg_ref);
+            }
+            IoctlCmd::FIONBIO(nonblocking) => {
+                // Go through the status flags so that FIONBIO and fcntl(F_SETFL)
+                // are consistent with each other
+                let mut status_flags = self.status_flags()?;
+                status_flags.set(StatusFlags::O_NONBLOCK, **nonblocking != 0);
+                self.set_status_flags(status_flags)?;
+                return Ok(0);
+            }
+            IoctlCmd::FIONREAD(arg_ref) if self.is_read_shutdown() => {
+                // No more data can be read after shutdown
+                **arg_ref = 0;
+                return Ok(0);
+            }
+            _ => {}
         }
 
         let cmd_num = cmd.cmd_num() as c_int;
@@ -46,8 +62,8 @@ impl HostSocket {
             // If ifc_req is NULL, SIOCGIFCONF returns the necessary buffer
             // size in bytes for receiving all available addresses in ifc_len
             // which is irrelevant to the orginal ifc_len.
-            if !arg_ref.ifc_buf.is_null() {
-                assert!(arg_ref.ifc_len >= recv_len);
+            if !arg_ref.ifc_buf.is_null() && arg_ref.ifc_len < recv_len {
+                return_errno!(EINVAL, "invalid data from host");
             }
 
             arg_ref.ifc_len = recv_len;
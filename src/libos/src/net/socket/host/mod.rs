@@ -1,13 +1,15 @@
 use std::any::Any;
+use std::fmt;
 use std::io::{Read, Seek, SeekFrom, Write};
 use std::mem;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 use atomic::Atomic;
 
 use super::*;
 use crate::fs::{
-    occlum_ocall_ioctl, AccessMode, CreationFlags, File, FileRef, HostFd, IoEvents, IoNotifier,
-    IoctlCmd, StatusFlags,
+    occlum_ocall_ioctl, AccessMode, AtomicIoEvents, CreationFlags, File, FileRef, HostFd, IoEvents,
+    IoNotifier, IoctlCmd, StatusFlags,
 };
 
 use crate::process::IO_BUF_SIZE;
@@ -16,13 +18,25 @@ mod ioctl_impl;
 mod recv;
 mod send;
 mod socket_file;
+mod sockopt_impl;
 
 /// Native linux socket
-#[derive(Debug)]
 pub struct HostSocket {
     host_fd: HostFd,
     host_events: Atomic<IoEvents>,
     notifier: IoNotifier,
+    domain: AddressFamily,
+    socket_type: SocketType,
+    is_listening: AtomicBool,
+    is_read_shutdown: AtomicBool,
+    is_write_shutdown: AtomicBool,
+    // Cached states of the host socket to show them without OCalls
+    is_nonblocking: AtomicBool,
+    tcp_nodelay: AtomicBool,
+    tcp_cork: AtomicBool,
+    // Cached local and peer addresses to save OCalls
+    addr: SgxMutex<Option<SockAddr>>,
+    peer_addr: SgxMutex<Option<SockAddr>>,
 }
 
 impl HostSocket {
@@ -38,16 +52,47 @@ impl HostSocket {
             protocol
         )) as FileDesc;
         let host_fd = HostFd::new(raw_host_fd);
-        Ok(HostSocket::from_host_fd(host_fd)?)
+        let nonblocking = file_flags.contains(FileFlags::SOCK_NONBLOCK);
+        let socket = HostSocket::from_host_fd(host_fd, domain, socket_type, nonblocking)?;
+
+        // Linux accepts both IPv4 and IPv6 traffic on an IPv6 socket by
+        // default. Do not let the sysctl of the host change this default.
+        if domain == AddressFamily::INET6 {
+            socket.set_ipv6_only(false)?;
+        }
+        Ok(socket)
     }
 
-    fn from_host_fd(host_fd: HostFd) -> Result<HostSocket> {
+    fn from_host_fd(
+        host_fd: HostFd,
+        domain: AddressFamily,
+        socket_type: SocketType,
+        nonblocking: bool,
+    ) -> Result<HostSocket> {
         let host_events = Atomic::new(IoEvents::empty());
         let notifier = IoNotifier::new();
+        let is_listening = AtomicBool::new(false);
+        let is_read_shutdown = AtomicBool::new(false);
+        let is_write_shutdown = AtomicBool::new(false);
+        let is_nonblocking = AtomicBool::new(nonblocking);
+        let tcp_nodelay = AtomicBool::new(false);
+        let tcp_cork = AtomicBool::new(false);
+        let addr = SgxMutex::new(None);
+        let peer_addr = SgxMutex::new(None);
         Ok(Self {
             host_fd,
             host_events,
             notifier,
+            domain,
+            socket_type,
+            is_listening,
+            is_read_shutdown,
+            is_write_shutdown,
+            is_nonblocking,
+            tcp_nodelay,
+            tcp_cork,
+            addr,
+            peer_addr,
         })
     }
 
@@ -59,14 +104,36 @@ impl HostSocket {
             addr_ptr as *const libc::sockaddr,
             addr_len as u32
         ));
+        *self.addr.lock().unwrap() = None;
         Ok(())
     }
 
     pub fn listen(&self, backlog: i32) -> Result<()> {
         let ret = try_libc!(libc::ocall::listen(self.raw_host_fd() as i32, backlog));
+        self.is_listening.store(true, Ordering::Release);
         Ok(())
     }
 
+    pub fn domain(&self) -> AddressFamily {
+        self.domain
+    }
+
+    pub fn socket_type(&self) -> SocketType {
+        self.socket_type
+    }
+
+    pub fn is_read_shutdown(&self) -> bool {
+        self.is_read_shutdown.load(Ordering::Acquire)
+    }
+
+    pub fn is_write_shutdown(&self) -> bool {
+        self.is_write_shutdown.load(Ordering::Acquire)
+    }
+
+    pub fn is_listening(&self) -> bool {
+        self.is_listening.load(Ordering::Acquire)
+    }
+
     pub fn accept(&self, flags: FileFlags) -> Result<(Self, Option<SockAddr>)> {
         let mut sockaddr = SockAddr::default();
         let mut addr_len = sockaddr.len();
@@ -85,9 +152,23 @@ impl HostSocket {
         } else {
             None
         };
-        Ok((HostSocket::from_host_fd(host_fd)?, addr_option))
+        let nonblocking = flags.contains(FileFlags::SOCK_NONBLOCK);
+        let new_socket =
+            HostSocket::from_host_fd(host_fd, self.domain, self.socket_type, nonblocking)?;
+        // The accepted socket inherits TCP_NODELAY from the listening socket
+        new_socket
+            .tcp_nodelay
+            .store(self.tcp_nodelay.load(Ordering::Acquire), Ordering::Release);
+        *new_socket.peer_addr.lock().unwrap() = addr_option;
+        Ok((new_socket, addr_option))
     }
 
+    /// Connect the socket to the address.
+    ///
+    /// Like Linux, connecting a datagram socket to an `AF_UNSPEC` address
+    /// dissolves the association with its peer. After that, getpeername fails
+    /// with `ENOTCONN` and sending without a destination fails with
+    /// `EDESTADDRREQ`.
     pub fn connect(&self, addr: &Option<SockAddr>) -> Result<()> {
         debug!("connect: host_fd: {}, addr {:?}", self.raw_host_fd(), addr);
 
@@ -97,12 +178,88 @@ impl HostSocket {
             (std::ptr::null(), 0)
         };
 
-        let ret = try_libc!(libc::ocall::connect(
+        let ret =
+            unsafe { libc::ocall::connect(self.raw_host_fd() as i32, addr_ptr, addr_len as u32) };
+        // Connecting may bind the socket or change its local and peer addresses,
+        // even if it fails, e.g., with EINPROGRESS
+        *self.addr.lock().unwrap() = None;
+        *self.peer_addr.lock().unwrap() = None;
+        if ret < 0 {
+            let errno = Errno::from(unsafe { libc::errno() } as u32);
+            if errno == EINPROGRESS {
+                // The connection of a nonblocking socket is established asynchronously. The
+                // cached events of the unconnected socket are stale until the host reports the
+                // completion, after which the result can be retrieved by SO_ERROR.
+                self.host_events.update(
+                    &IoEvents::empty(),
+                    &(IoEvents::OUT | IoEvents::HUP),
+                    Ordering::Release,
+                );
+            }
+            return_errno!(errno, "connect failed");
+        }
+        Ok(())
+    }
+
+    /// Returns the local address.
+    ///
+    /// The address is cached once it is decided, i.e., with a nonzero port.
+    /// An unbound socket, whose address is the wildcard one with port zero,
+    /// always queries the host since the address is decided later by the host.
+    pub fn addr(&self) -> Result<SockAddr> {
+        let mut cached_addr = self.addr.lock().unwrap();
+        if let Some(addr) = *cached_addr {
+            return Ok(addr);
+        }
+
+        let mut addr = SockAddr::default();
+        let mut addr_len = addr.len() as u32;
+        try_libc!(libc::ocall::getsockname(
             self.raw_host_fd() as i32,
-            addr_ptr,
-            addr_len as u32
+            addr.as_mut_ptr(),
+            &mut addr_len
         ));
-        Ok(())
+        addr.set_len(addr_len as usize)?;
+        if addr.port().map_or(false, |port| port != 0) {
+            *cached_addr = Some(addr);
+        }
+        Ok(addr)
+    }
+
+    /// Returns the address of the peer.
+    ///
+    /// The address is cached once the socket is connected. The cache is
+    /// cleared when the socket is shut down or a hangup is observed, after
+    /// which the host is always queried, so `ENOTCONN` is returned like Linux
+    /// once the connection is closed.
+    pub fn peer_addr(&self) -> Result<SockAddr> {
+        let mut cached_addr = self.peer_addr.lock().unwrap();
+        if let Some(addr) = *cached_addr {
+            return Ok(addr);
+        }
+
+        let mut addr = SockAddr::default();
+        let mut addr_len = addr.len() as u32;
+        try_libc!(libc::ocall::getpeername(
+            self.raw_host_fd() as i32,
+            addr.as_mut_ptr(),
+            &mut addr_len
+        ));
+        addr.set_len(addr_len as usize)?;
+        if !self.is_closing() {
+            *cached_addr = Some(addr);
+        }
+        Ok(addr)
+    }
+
+    // Whether the connection may be closed, so the peer address is not cached
+    fn is_closing(&self) -> bool {
+        self.is_read_shutdown()
+            || self.is_write_shutdown()
+            || self
+                .host_events
+                .load(Ordering::Acquire)
+                .contains(IoEvents::HUP)
     }
 
     pub fn sendto(
@@ -131,12 +288,34 @@ impl HostSocket {
         Ok((bytes_recv, addr_option))
     }
 
+    fn set_ipv6_only(&self, ipv6_only: bool) -> Result<()> {
+        const IPPROTO_IPV6: c_int = 41;
+        const IPV6_V6ONLY: c_int = 26;
+
+        let optval = ipv6_only as c_int;
+        try_libc!(libc::ocall::setsockopt(
+            self.raw_host_fd() as i32,
+            IPPROTO_IPV6,
+            IPV6_V6ONLY,
+            &optval as *const c_int as *const c_void,
+            mem::size_of::<c_int>() as u32
+        ));
+        Ok(())
+    }
+
     pub fn raw_host_fd(&self) -> FileDesc {
         self.host_fd.to_raw()
     }
 
     pub fn shutdown(&self, how: HowToShut) -> Result<()> {
         try_libc!(libc::ocall::shutdown(self.raw_host_fd() as i32, how.bits()));
+        if how.to_shut_read() {
+            self.is_read_shutdown.store(true, Ordering::Release);
+        }
+        if how.to_shut_write() {
+            self.is_write_shutdown.store(true, Ordering::Release);
+        }
+        *self.peer_addr.lock().unwrap() = None;
         Ok(())
     }
 }
@@ -145,6 +324,27 @@ pub trait HostSocketType {
     fn as_host_socket(&self) -> Result<&HostSocket>;
 }
 
+/// Shows the states of the socket cached in the LibOS, so that no OCall is
+/// made. The data buffered in the host is never shown.
+impl fmt::Debug for HostSocket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HostSocket")
+            .field("host_fd", &self.raw_host_fd())
+            .field("domain", &self.domain)
+            .field("socket_type", &self.socket_type)
+            .field("addr", &*self.addr.lock().unwrap())
+            .field("peer_addr", &*self.peer_addr.lock().unwrap())
+            .field("is_listening", &self.is_listening())
+            .field("is_read_shutdown", &self.is_read_shutdown())
+            .field("is_write_shutdown", &self.is_write_shutdown())
+            .field("nonblocking", &self.is_nonblocking.load(Ordering::Relaxed))
+            .field("tcp_nodelay", &self.tcp_nodelay.load(Ordering::Relaxed))
+            .field("tcp_cork", &self.tcp_cork.load(Ordering::Relaxed))
+            .field("host_events", &self.host_events.load(Ordering::Relaxed))
+            .finish()
+    }
+}
+
 impl HostSocketType for FileRef {
     fn as_host_socket(&self) -> Result<&HostSocket> {
         self.as_any()
@@ -7,7 +7,13 @@ impl HostSocket {
         Ok(bytes_recvd)
     }
 
-    pub fn recvmsg<'a, 'b>(&self, msg: &'b mut MsgHdrMut<'a>, flags: RecvFlags) -> Result<usize> {
+    /// Receive a message and return the number of bytes received and the
+    /// resulting message flags, e.g., `MSG_TRUNC` for a truncated datagram.
+    pub fn recvmsg<'a, 'b>(
+        &self,
+        msg: &'b mut MsgHdrMut<'a>,
+        flags: RecvFlags,
+    ) -> Result<(usize, MsgHdrFlags)> {
         // Do OCall-based recvmsg
         let (bytes_recvd, namelen_recvd, controllen_recvd, flags_recvd) = {
             // Acquire mutable references to the name and control buffers
@@ -16,12 +22,11 @@ impl HostSocket {
             self.do_recvmsg(iovs.as_slices_mut(), flags, name, control)?
         };
 
-        // Update the output lengths and flags
+        // Update the output lengths
         msg.set_name_len(namelen_recvd)?;
         msg.set_control_len(controllen_recvd)?;
-        msg.set_flags(flags_recvd);
 
-        Ok(bytes_recvd)
+        Ok((bytes_recvd, flags_recvd))
     }
 
     pub(super) fn do_recvmsg(
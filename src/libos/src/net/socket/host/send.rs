@@ -24,6 +24,12 @@ impl HostSocket {
         control: Option<&[u8]>,
     ) -> Result<usize> {
         let current = current!();
+        // Fail early without an OCall, as the host would. Like Linux, no
+        // SIGPIPE is raised for a datagram socket.
+        if self.socket_type() == SocketType::DGRAM && self.is_write_shutdown() {
+            return_errno!(EPIPE, "the socket is shut down for writing");
+        }
+
         let data_length = data.iter().map(|s| s.len()).sum();
         let mut ocall_alloc;
         // Allocated slice in untrusted memory region
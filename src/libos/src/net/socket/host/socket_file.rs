@@ -69,6 +69,10 @@ impl File for HostSocket {
             libc::F_SETFL,
             raw_status_flags as c_int
         ));
+        self.is_nonblocking.store(
+            new_status_flags.contains(StatusFlags::O_NONBLOCK),
+            Ordering::Relaxed,
+        );
         Ok(())
     }
 
@@ -86,6 +90,10 @@ impl File for HostSocket {
 
     fn update_host_events(&self, ready: &IoEvents, mask: &IoEvents, trigger_notifier: bool) {
         self.host_events.update(ready, mask, Ordering::Release);
+        // The peer address is no longer valid once the connection is closed
+        if ready.contains(IoEvents::HUP) {
+            *self.peer_addr.lock().unwrap() = None;
+        }
 
         if trigger_notifier {
             self.notifier.broadcast(ready);
@@ -0,0 +1,168 @@
+use super::*;
+use std::sync::atomic::Ordering;
+
+impl HostSocket {
+    /// Set a socket option of the host socket.
+    ///
+    /// The options not known by the LibOS, i.e., `SockOptName::OTHER`, are
+    /// forwarded to the host verbatim for compatibility. This pass-through
+    /// applies only to IPv4 and IPv6 sockets, and can be turned off by
+    /// `net.sockopt_pass_through` in Occlum.json, in which case such options
+    /// fail with `ENOPROTOOPT`. Note that the host is untrusted: the LibOS
+    /// cannot check what such an option does, e.g., that the traffic is still
+    /// encrypted or delivered to the right place, and the value of the option
+    /// read back by getsockopt is just bytes given by the host, which are only
+    /// checked not to exceed the buffer.
+    pub fn setsockopt(&self, level: c_int, optname: c_int, optval: &[u8]) -> Result<()> {
+        let optname = SockOptName::from_raw(level, optname);
+        match optname {
+            SockOptName::SO_REUSEADDR => {
+                if self.is_listening() {
+                    warn!("SO_REUSEADDR is set after listen, which takes no effect");
+                }
+            }
+            SockOptName::SO_ERROR => return_errno!(ENOPROTOOPT, "SO_ERROR is read-only"),
+            SockOptName::TCP_INFO => return_errno!(ENOPROTOOPT, "TCP_INFO is read-only"),
+            SockOptName::SO_TYPE
+            | SockOptName::SO_DOMAIN
+            | SockOptName::SO_PROTOCOL
+            | SockOptName::SO_ACCEPTCONN => {
+                return_errno!(ENOPROTOOPT, "the option is read-only")
+            }
+            SockOptName::SO_RCVBUF | SockOptName::SO_SNDBUF => {
+                // The size is doubled by the host, and the doubled size is
+                // returned by getsockopt, just as on Linux
+                optval_to_buf_size(optval)?;
+            }
+            SockOptName::SO_LINGER => {
+                // The linger is honored by the host when the host socket is
+                // closed. Like Linux, closing a socket with a nonzero linger
+                // timeout blocks until the data is sent or the timeout is up,
+                // even if the socket is nonblocking. And a zero linger timeout
+                // resets the connection immediately, discarding unsent data.
+                if optval.len() < mem::size_of::<libc::linger>() {
+                    return_errno!(EINVAL, "optlen is too short for SO_LINGER");
+                }
+            }
+            SockOptName::IP_TTL => {
+                // Like Linux, -1 means the default TTL of the system
+                let ttl = optval_to_int(optval)?;
+                if ttl != -1 && (ttl < 1 || ttl > 255) {
+                    return_errno!(EINVAL, "the TTL should be within 1 to 255");
+                }
+            }
+            SockOptName::IPV6_UNICAST_HOPS | SockOptName::IPV6_TCLASS => {
+                // Like Linux, -1 means the default value of the system
+                let val = optval_to_int(optval)?;
+                if val < -1 || val > 255 {
+                    return_errno!(EINVAL, "the value should be within -1 to 255");
+                }
+            }
+            SockOptName::TCP_NODELAY => {
+                self.check_stream_only(&optname)?;
+                optval_to_int(optval)?;
+            }
+            SockOptName::TCP_CORK => {
+                // The corked data is buffered by the host, which also sends
+                // it out when the socket is uncorked or closed
+                self.check_stream_only(&optname)?;
+            }
+            SockOptName::OTHER(level, name) => self.check_pass_through(level, name)?,
+            _ => {}
+        }
+
+        self.do_setsockopt(&optname, optval)?;
+        // Cache the value only after the host accepts it, so that getsockopt
+        // never reports a value rejected by the host
+        match optname {
+            SockOptName::TCP_NODELAY => self
+                .tcp_nodelay
+                .store(optval_to_int(optval)? != 0, Ordering::Release),
+            SockOptName::TCP_CORK => self
+                .tcp_cork
+                .store(optval_to_int(optval)? != 0, Ordering::Relaxed),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    pub fn getsockopt(&self, level: c_int, optname: c_int, optval: &mut [u8]) -> Result<usize> {
+        let optname = SockOptName::from_raw(level, optname);
+        match optname {
+            // The pending error, e.g., of a failed nonblocking connect, is
+            // tracked by the host and cleared once it is read
+            SockOptName::SO_ERROR => self.do_getsockopt(&optname, optval),
+            SockOptName::SO_PEERCRED => {
+                // The credentials of host processes make no sense inside the LibOS
+                return_errno!(EOPNOTSUPP, "SO_PEERCRED is only supported by unix socket")
+            }
+            SockOptName::TCP_NODELAY => {
+                self.check_stream_only(&optname)?;
+                let nodelay = self.tcp_nodelay.load(Ordering::Acquire);
+                Ok(int_to_optval(nodelay as c_int, optval))
+            }
+            SockOptName::TCP_CORK => {
+                self.check_stream_only(&optname)?;
+                self.do_getsockopt(&optname, optval)
+            }
+            SockOptName::TCP_INFO => {
+                // The connection state and statistics, e.g., RTT, congestion
+                // window and retransmissions, are all maintained by the host.
+                // Like Linux, the info is truncated to the buffer length.
+                self.check_stream_only(&optname)?;
+                self.do_getsockopt(&optname, optval)
+            }
+            SockOptName::OTHER(level, name) => {
+                self.check_pass_through(level, name)?;
+                self.do_getsockopt(&optname, optval)
+            }
+            _ => self.do_getsockopt(&optname, optval),
+        }
+    }
+
+    fn check_pass_through(&self, level: c_int, name: c_int) -> Result<()> {
+        if !crate::config::LIBOS_CONFIG.net.sockopt_pass_through {
+            return_errno!(ENOPROTOOPT, "the pass-through of socket options is off");
+        }
+        if !matches!(self.domain(), AddressFamily::INET | AddressFamily::INET6) {
+            return_errno!(ENOPROTOOPT, "only options of IP sockets are passed through");
+        }
+        debug!("pass the option ({}, {}) through to the host", level, name);
+        Ok(())
+    }
+
+    fn check_stream_only(&self, optname: &SockOptName) -> Result<()> {
+        if self.socket_type() != SocketType::STREAM {
+            return_errno!(EOPNOTSUPP, "the option is only for stream sockets");
+        }
+        Ok(())
+    }
+
+    fn do_setsockopt(&self, optname: &SockOptName, optval: &[u8]) -> Result<()> {
+        try_libc!(libc::ocall::setsockopt(
+            self.raw_host_fd() as i32,
+            optname.level(),
+            optname.name(),
+            optval.as_ptr() as *const c_void,
+            optval.len() as u32
+        ));
+        Ok(())
+    }
+
+    fn do_getsockopt(&self, optname: &SockOptName, optval: &mut [u8]) -> Result<usize> {
+        let max_optlen = optval.len() as u32;
+        let mut optlen = max_optlen;
+        try_libc!(libc::ocall::getsockopt(
+            self.raw_host_fd() as i32,
+            optname.level(),
+            optname.name(),
+            optval.as_mut_ptr() as *mut c_void,
+            &mut optlen as *mut u32
+        ));
+        // Check optlen returned from outside the enclave
+        if optlen > max_optlen {
+            return_errno!(EINVAL, "invalid optlen from host");
+        }
+        Ok(optlen as usize)
+    }
+}
@@ -8,14 +8,21 @@ mod msg;
 mod shutdown;
 mod socket_address;
 mod socket_type;
+mod sockopt;
 mod unix;
 
 pub use self::address_family::AddressFamily;
 pub use self::flags::{FileFlags, MsgHdrFlags, RecvFlags, SendFlags};
 pub use self::host::{HostSocket, HostSocketType};
 pub use self::iovs::{Iovs, IovsMut, SliceAsLibcIovec};
-pub use self::msg::{mmsghdr, msghdr, msghdr_mut, CMessages, CmsgData, MsgHdr, MsgHdrMut};
+pub use self::msg::{
+    mmsghdr, mmsghdr_mut, msghdr, msghdr_mut, CMessages, CmsgData, MsgHdr, MsgHdrMut,
+};
 pub use self::shutdown::HowToShut;
 pub use self::socket_address::SockAddr;
 pub use self::socket_type::SocketType;
+pub use self::sockopt::{
+    int_to_optval, optval_to_buf_size, optval_to_int, optval_to_timeout, timeout_to_optval,
+    SockOptName,
+};
 pub use self::unix::{socketpair, unix_socket, AsUnixSocket, UnixAddr};
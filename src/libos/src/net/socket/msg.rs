@@ -34,6 +34,13 @@ pub struct msghdr_mut {
     pub msg_flags: c_int,
 }
 
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct mmsghdr_mut {
+    pub msg_hdr: msghdr_mut,
+    pub msg_len: c_uint,
+}
+
 /// MsgHdr is a memory-safe, immutable wrapper of msghdr
 pub struct MsgHdr<'a> {
     name: Option<&'a [u8]>,
@@ -261,7 +268,7 @@ impl<'a> CMessages<'a> {
 /// Control message data of variable type. The data resides next to `cmsghdr`.
 pub enum CmsgData<'a> {
     ScmRights(ScmRights<'a>),
-    ScmCredentials,
+    ScmCredentials(libc::ucred),
 }
 
 impl<'a> CmsgData<'a> {
@@ -282,8 +289,14 @@ impl<'a> CmsgData<'a> {
     ///
     /// `data` must contain a valid control message and the control message must be type of
     /// `SOL_SOCKET` and level of `SCM_CREDENTIALS`.
-    unsafe fn as_credentials(_data: &'a [u8]) -> Self {
-        CmsgData::ScmCredentials
+    ///
+    /// Returns `None` if the data is too short to hold a `ucred`.
+    unsafe fn as_credentials(data: &'a [u8]) -> Option<Self> {
+        if data.len() < core::mem::size_of::<libc::ucred>() {
+            return None;
+        }
+        let ucred = core::ptr::read_unaligned(data.as_ptr() as *const libc::ucred);
+        Some(CmsgData::ScmCredentials(ucred))
     }
 
     fn try_from_cmsghdr(cmsg: &'a libc::cmsghdr) -> Option<Self> {
@@ -296,7 +309,7 @@ impl<'a> CmsgData<'a> {
             match (*cmsg).cmsg_level {
                 libc::SOL_SOCKET => match (*cmsg).cmsg_type {
                     libc::SCM_RIGHTS => Some(CmsgData::as_rights(data)),
-                    libc::SCM_CREDENTIALS => Some(CmsgData::as_credentials(data)),
+                    libc::SCM_CREDENTIALS => CmsgData::as_credentials(data),
                     _ => None,
                 },
                 _ => None,
@@ -1,4 +1,6 @@
 use super::*;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
+use std::str::FromStr;
 use std::*;
 
 #[derive(Copy, Clone)]
@@ -7,19 +9,94 @@ pub struct SockAddr {
     len: usize,
 }
 
-// TODO: add more fields
 impl fmt::Debug for SockAddr {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_struct("SockAddr")
-            .field(
-                "family",
-                &AddressFamily::try_from(self.storage.ss_family).unwrap(),
-            )
-            .field("len", &self.len)
-            .finish()
+        let mut d = f.debug_struct("SockAddr");
+        d.field("family", &self.family());
+        match self.family() {
+            Ok(AddressFamily::INET) => {
+                let addr = unsafe { &*(self.as_ptr() as *const libc::sockaddr_in) };
+                d.field("addr", &u32::from_be(addr.sin_addr.s_addr).to_be_bytes())
+                    .field("port", &u16::from_be(addr.sin_port));
+            }
+            Ok(AddressFamily::INET6) => {
+                let addr = unsafe { &*(self.as_ptr() as *const libc::sockaddr_in6) };
+                d.field("addr", &addr.sin6_addr.s6_addr)
+                    .field("port", &u16::from_be(addr.sin6_port))
+                    .field("scope_id", &addr.sin6_scope_id);
+            }
+            _ => {}
+        }
+        d.field("len", &self.len).finish()
+    }
+}
+
+/// Formats an IPv4 address as "1.2.3.4:80" and an IPv6 address as "[::1]:80".
+impl fmt::Display for SockAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.family() {
+            Ok(AddressFamily::INET) => {
+                let addr = unsafe { &*(self.as_ptr() as *const libc::sockaddr_in) };
+                let ip = Ipv4Addr::from(u32::from_be(addr.sin_addr.s_addr));
+                write!(f, "{}", SocketAddrV4::new(ip, u16::from_be(addr.sin_port)))
+            }
+            Ok(AddressFamily::INET6) => {
+                let addr = unsafe { &*(self.as_ptr() as *const libc::sockaddr_in6) };
+                let ip = Ipv6Addr::from(addr.sin6_addr.s6_addr);
+                let addr = SocketAddrV6::new(
+                    ip,
+                    u16::from_be(addr.sin6_port),
+                    addr.sin6_flowinfo,
+                    addr.sin6_scope_id,
+                );
+                write!(f, "{}", addr)
+            }
+            Ok(family) => write!(f, "{:?}", family),
+            Err(_) => write!(f, "unknown family {}", self.storage.ss_family),
+        }
+    }
+}
+
+/// Parses an IPv4 address like "1.2.3.4:80" or an IPv6 address like "[::1]:80".
+impl FromStr for SockAddr {
+    type Err = error::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let addr: SocketAddr = s
+            .parse()
+            .map_err(|_| errno!(EINVAL, "not a valid IP address and port"))?;
+
+        let mut sock_addr = Self::default();
+        match addr {
+            SocketAddr::V4(addr) => {
+                let raw = unsafe { &mut *(sock_addr.as_mut_ptr() as *mut libc::sockaddr_in) };
+                raw.sin_family = AddressFamily::INET as libc::sa_family_t;
+                raw.sin_port = addr.port().to_be();
+                raw.sin_addr.s_addr = u32::from(*addr.ip()).to_be();
+                sock_addr.len = mem::size_of::<libc::sockaddr_in>();
+            }
+            SocketAddr::V6(addr) => {
+                let raw = unsafe { &mut *(sock_addr.as_mut_ptr() as *mut libc::sockaddr_in6) };
+                raw.sin6_family = AddressFamily::INET6 as libc::sa_family_t;
+                raw.sin6_port = addr.port().to_be();
+                raw.sin6_flowinfo = addr.flowinfo();
+                raw.sin6_addr.s6_addr = addr.ip().octets();
+                raw.sin6_scope_id = addr.scope_id();
+                sock_addr.len = mem::size_of::<libc::sockaddr_in6>();
+            }
+        }
+        Ok(sock_addr)
+    }
+}
+
+impl PartialEq for SockAddr {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_slice() == other.as_slice()
     }
 }
 
+impl Eq for SockAddr {}
+
 impl SockAddr {
     // Caller should guarentee the sockaddr and addr_len are valid
     pub unsafe fn try_from_raw(
@@ -48,19 +125,66 @@ impl SockAddr {
                     return_errno!(EINVAL, "wrong ipv6 address length.");
                 }
             }
+            // Used to dissolve the association of a connected socket
+            AddressFamily::UNSPEC => {}
             _ => warn!("address family not checked"),
         }
 
-        let mut storage = mem::MaybeUninit::<libc::sockaddr_storage>::uninit();
+        // Zero the storage so that the omitted sin6_scope_id reads as 0
+        let mut storage: libc::sockaddr_storage = mem::zeroed();
         ptr::copy_nonoverlapping(
             sockaddr as *const _ as *const u8,
-            storage.as_mut_ptr() as *mut u8,
+            &mut storage as *mut _ as *mut u8,
             addr_len as usize,
         );
-        Ok(Self {
-            storage: storage.assume_init(),
+        let sock_addr = Self {
+            storage,
             len: addr_len as usize,
-        })
+        };
+
+        if sock_addr.is_ipv6_link_local() && sock_addr.ipv6_scope_id() == Some(0) {
+            warn!("IPv6 link-local address without a scope id");
+        }
+        Ok(sock_addr)
+    }
+
+    pub fn family(&self) -> Result<AddressFamily> {
+        AddressFamily::try_from(self.storage.ss_family)
+    }
+
+    /// Returns the port of an IPv4 or IPv6 address.
+    pub fn port(&self) -> Option<u16> {
+        match self.family().ok()? {
+            AddressFamily::INET => {
+                let addr = unsafe { &*(self.as_ptr() as *const libc::sockaddr_in) };
+                Some(u16::from_be(addr.sin_port))
+            }
+            AddressFamily::INET6 => {
+                let addr = unsafe { &*(self.as_ptr() as *const libc::sockaddr_in6) };
+                Some(u16::from_be(addr.sin6_port))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the scope id of an IPv6 address, which identifies the interface
+    /// of a link-local address.
+    pub fn ipv6_scope_id(&self) -> Option<u32> {
+        if self.family().ok()? != AddressFamily::INET6 {
+            return None;
+        }
+        let addr = unsafe { &*(self.as_ptr() as *const libc::sockaddr_in6) };
+        Some(addr.sin6_scope_id)
+    }
+
+    /// Whether the address is an IPv6 link-local address, i.e., in fe80::/10.
+    pub fn is_ipv6_link_local(&self) -> bool {
+        if self.family().ok() != Some(AddressFamily::INET6) {
+            return false;
+        }
+        let addr = unsafe { &*(self.as_ptr() as *const libc::sockaddr_in6) };
+        let octets = addr.sin6_addr.s6_addr;
+        octets[0] == 0xfe && (octets[1] & 0xc0) == 0x80
     }
 
     pub fn as_ptr_and_len(&self) -> (*const libc::sockaddr, usize) {
@@ -0,0 +1,183 @@
+use super::*;
+use std::time::Duration;
+use std::{mem, ptr, slice};
+use time::timeval_t;
+
+/// The level and name of a socket option.
+///
+/// Only the socket options that need special treatment by the LibOS are
+/// listed. All other options are represented by `OTHER`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[allow(non_camel_case_types)]
+pub enum SockOptName {
+    SO_REUSEADDR,
+    SO_ERROR,
+    SO_PEERCRED,
+    SO_PASSCRED,
+    SO_RCVTIMEO,
+    SO_SNDTIMEO,
+    SO_RCVBUF,
+    SO_SNDBUF,
+    SO_LINGER,
+    SO_TYPE,
+    SO_DOMAIN,
+    SO_PROTOCOL,
+    SO_ACCEPTCONN,
+    IP_TOS,
+    IP_TTL,
+    IPV6_TCLASS,
+    IPV6_UNICAST_HOPS,
+    TCP_NODELAY,
+    TCP_CORK,
+    TCP_INFO,
+    OTHER(c_int, c_int),
+}
+
+impl SockOptName {
+    pub fn from_raw(level: c_int, optname: c_int) -> Self {
+        match (level, optname) {
+            (libc::SOL_SOCKET, libc::SO_REUSEADDR) => Self::SO_REUSEADDR,
+            (libc::SOL_SOCKET, libc::SO_ERROR) => Self::SO_ERROR,
+            (libc::SOL_SOCKET, libc::SO_PEERCRED) => Self::SO_PEERCRED,
+            (libc::SOL_SOCKET, libc::SO_PASSCRED) => Self::SO_PASSCRED,
+            (libc::SOL_SOCKET, libc::SO_RCVTIMEO) => Self::SO_RCVTIMEO,
+            (libc::SOL_SOCKET, libc::SO_SNDTIMEO) => Self::SO_SNDTIMEO,
+            (libc::SOL_SOCKET, libc::SO_RCVBUF) => Self::SO_RCVBUF,
+            (libc::SOL_SOCKET, libc::SO_SNDBUF) => Self::SO_SNDBUF,
+            (libc::SOL_SOCKET, libc::SO_LINGER) => Self::SO_LINGER,
+            (libc::SOL_SOCKET, libc::SO_TYPE) => Self::SO_TYPE,
+            (libc::SOL_SOCKET, libc::SO_DOMAIN) => Self::SO_DOMAIN,
+            (libc::SOL_SOCKET, libc::SO_PROTOCOL) => Self::SO_PROTOCOL,
+            (libc::SOL_SOCKET, libc::SO_ACCEPTCONN) => Self::SO_ACCEPTCONN,
+            (libc::IPPROTO_IP, libc::IP_TOS) => Self::IP_TOS,
+            (libc::IPPROTO_IP, libc::IP_TTL) => Self::IP_TTL,
+            (libc::IPPROTO_IPV6, libc::IPV6_TCLASS) => Self::IPV6_TCLASS,
+            (libc::IPPROTO_IPV6, libc::IPV6_UNICAST_HOPS) => Self::IPV6_UNICAST_HOPS,
+            (libc::IPPROTO_TCP, libc::TCP_NODELAY) => Self::TCP_NODELAY,
+            (libc::IPPROTO_TCP, libc::TCP_CORK) => Self::TCP_CORK,
+            (libc::IPPROTO_TCP, libc::TCP_INFO) => Self::TCP_INFO,
+            _ => Self::OTHER(level, optname),
+        }
+    }
+
+    pub fn level(&self) -> c_int {
+        match self {
+            Self::SO_REUSEADDR
+            | Self::SO_ERROR
+            | Self::SO_PEERCRED
+            | Self::SO_PASSCRED
+            | Self::SO_RCVTIMEO
+            | Self::SO_SNDTIMEO
+            | Self::SO_RCVBUF
+            | Self::SO_SNDBUF
+            | Self::SO_LINGER
+            | Self::SO_TYPE
+            | Self::SO_DOMAIN
+            | Self::SO_PROTOCOL
+            | Self::SO_ACCEPTCONN => libc::SOL_SOCKET,
+            Self::IP_TOS | Self::IP_TTL => libc::IPPROTO_IP,
+            Self::IPV6_TCLASS | Self::IPV6_UNICAST_HOPS => libc::IPPROTO_IPV6,
+            Self::TCP_NODELAY | Self::TCP_CORK | Self::TCP_INFO => libc::IPPROTO_TCP,
+            Self::OTHER(level, _) => *level,
+        }
+    }
+
+    pub fn name(&self) -> c_int {
+        match self {
+            Self::SO_REUSEADDR => libc::SO_REUSEADDR,
+            Self::SO_ERROR => libc::SO_ERROR,
+            Self::SO_PEERCRED => libc::SO_PEERCRED,
+            Self::SO_PASSCRED => libc::SO_PASSCRED,
+            Self::SO_RCVTIMEO => libc::SO_RCVTIMEO,
+            Self::SO_SNDTIMEO => libc::SO_SNDTIMEO,
+            Self::SO_RCVBUF => libc::SO_RCVBUF,
+            Self::SO_SNDBUF => libc::SO_SNDBUF,
+            Self::SO_LINGER => libc::SO_LINGER,
+            Self::SO_TYPE => libc::SO_TYPE,
+            Self::SO_DOMAIN => libc::SO_DOMAIN,
+            Self::SO_PROTOCOL => libc::SO_PROTOCOL,
+            Self::SO_ACCEPTCONN => libc::SO_ACCEPTCONN,
+            Self::IP_TOS => libc::IP_TOS,
+            Self::IP_TTL => libc::IP_TTL,
+            Self::IPV6_TCLASS => libc::IPV6_TCLASS,
+            Self::IPV6_UNICAST_HOPS => libc::IPV6_UNICAST_HOPS,
+            Self::TCP_NODELAY => libc::TCP_NODELAY,
+            Self::TCP_CORK => libc::TCP_CORK,
+            Self::TCP_INFO => libc::TCP_INFO,
+            Self::OTHER(_, optname) => *optname,
+        }
+    }
+}
+
+/// Read an integer option value given by the user.
+pub fn optval_to_int(optval: &[u8]) -> Result<c_int> {
+    if optval.len() < mem::size_of::<c_int>() {
+        return_errno!(EINVAL, "optlen is too short for an integer option");
+    }
+    let mut bytes = [0_u8; mem::size_of::<c_int>()];
+    bytes.copy_from_slice(&optval[..mem::size_of::<c_int>()]);
+    Ok(c_int::from_ne_bytes(bytes))
+}
+
+/// Read a buffer size option value, i.e., `SO_RCVBUF` or `SO_SNDBUF`, given by
+/// the user.
+///
+/// Unlike Linux, which treats a negative size as a huge one, a negative size
+/// is rejected with `EINVAL`.
+pub fn optval_to_buf_size(optval: &[u8]) -> Result<usize> {
+    let size = optval_to_int(optval)?;
+    if size < 0 {
+        return_errno!(EINVAL, "the buffer size is negative");
+    }
+    Ok(size as usize)
+}
+
+/// Write an integer option value to the user's buffer.
+///
+/// Like Linux, the value is truncated if the buffer is too short. Returns the
+/// number of bytes written.
+pub fn int_to_optval(val: c_int, optval: &mut [u8]) -> usize {
+    let bytes = val.to_ne_bytes();
+    let len = optval.len().min(bytes.len());
+    optval[..len].copy_from_slice(&bytes[..len]);
+    len
+}
+
+/// Read a timeout option value, i.e., `struct timeval`, given by the user.
+///
+/// A zero timeout means no timeout, i.e., `None`.
+pub fn optval_to_timeout(optval: &[u8]) -> Result<Option<Duration>> {
+    if optval.len() < mem::size_of::<timeval_t>() {
+        return_errno!(EINVAL, "optlen is too short for a timeout option");
+    }
+    let timeval = unsafe { ptr::read_unaligned(optval.as_ptr() as *const timeval_t) };
+    // Like Linux, a negative timeout makes blocking operations fail immediately
+    if timeval.sec() < 0 {
+        return Ok(Some(Duration::from_secs(0)));
+    }
+    timeval.validate()?;
+
+    let timeout = timeval.as_duration();
+    if timeout == Duration::from_secs(0) {
+        Ok(None)
+    } else {
+        Ok(Some(timeout))
+    }
+}
+
+/// Write a timeout option value to the user's buffer.
+///
+/// Like `int_to_optval`, the value is truncated if the buffer is too short.
+/// Returns the number of bytes written.
+pub fn timeout_to_optval(timeout: Option<Duration>, optval: &mut [u8]) -> usize {
+    let timeval = timeout.map(timeval_t::from).unwrap_or_default();
+    let bytes = unsafe {
+        slice::from_raw_parts(
+            &timeval as *const timeval_t as *const u8,
+            mem::size_of::<timeval_t>(),
+        )
+    };
+    let len = optval.len().min(bytes.len());
+    optval[..len].copy_from_slice(&bytes[..len]);
+    len
+}
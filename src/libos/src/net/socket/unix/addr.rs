@@ -1,6 +1,7 @@
 use super::*;
 use std::path::{Path, PathBuf};
-use std::{cmp, mem, slice, str};
+use std::str::FromStr;
+use std::{cmp, fmt, mem, slice};
 
 const MAX_PATH_LEN: usize = 108;
 const SUN_FAMILY_LEN: usize = mem::size_of::<libc::sa_family_t>();
@@ -11,7 +12,8 @@ lazy_static! {
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub enum Addr {
     File(Option<usize>, UnixPath), // An optional inode number and path. Use inode if there is one.
-    Abstract(String),
+    // The name of an abstract address, which may contain any bytes including nulls
+    Abstract(Vec<u8>),
 }
 
 impl Addr {
@@ -45,9 +47,7 @@ impl Addr {
             let path_slice =
                 slice::from_raw_parts(path_ptr as *const u8, addr_len - *SUN_PATH_OFFSET - 1);
 
-            Ok(Self::Abstract(
-                str::from_utf8(&path_slice).unwrap().to_string(),
-            ))
+            Ok(Self::Abstract(path_slice.to_vec()))
         } else {
             let path_cstr = CStr::from_ptr(sun_path.as_ptr());
             if path_cstr.to_bytes_with_nul().len() > MAX_PATH_LEN {
@@ -68,16 +68,11 @@ impl Addr {
     }
 
     pub fn raw_len(&self) -> usize {
-        /// The '/0' at the end of Self::File counts
-        self.path_str().len()
-            + 1
-            + *SUN_PATH_OFFSET
-    }
-
-    pub fn path_str(&self) -> &str {
         match self {
-            Self::File(_, unix_path) => &unix_path.path_str(),
-            Self::Abstract(path) => &path,
+            // The '\0' at the end of Self::File counts
+            Self::File(_, unix_path) => unix_path.path_str().len() + 1 + *SUN_PATH_OFFSET,
+            // The '\0' at the beginning of Self::Abstract counts
+            Self::Abstract(name) => 1 + name.len() + *SUN_PATH_OFFSET,
         }
     }
 
@@ -96,11 +91,11 @@ impl Addr {
                     .copy_from_slice(unsafe { &*(path_str.as_bytes() as *const _ as *const [i8]) });
                 buf_len + *SUN_PATH_OFFSET + 1
             }
-            Self::Abstract(path_str) => {
+            Self::Abstract(name) => {
                 addr.sun_path[0] = 0;
-                let buf_len = path_str.len() + 1;
+                let buf_len = name.len() + 1;
                 addr.sun_path[1..buf_len]
-                    .copy_from_slice(unsafe { &*(path_str.as_bytes() as *const _ as *const [i8]) });
+                    .copy_from_slice(unsafe { &*(name.as_slice() as *const _ as *const [i8]) });
                 buf_len + *SUN_PATH_OFFSET
             }
         };
@@ -109,6 +104,43 @@ impl Addr {
     }
 }
 
+/// Formats a pathname address as the path and an abstract address as its name
+/// prefixed with "@", as shown by tools like `ss`.
+impl fmt::Display for Addr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::File(_, unix_path) => write!(f, "{}", unix_path.path_str()),
+            Self::Abstract(name) => write!(f, "@{}", String::from_utf8_lossy(name)),
+        }
+    }
+}
+
+/// Parses a name prefixed with "@" as an abstract address and anything else
+/// as a pathname address.
+impl FromStr for Addr {
+    type Err = error::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if s.starts_with('@') {
+            let name = &s.as_bytes()[1..];
+            // The leading null of the abstract name is counted
+            if name.len() + 1 > MAX_PATH_LEN {
+                return_errno!(EINVAL, "the address is too long.");
+            }
+            return Ok(Self::Abstract(name.to_vec()));
+        }
+
+        if s.is_empty() {
+            return_errno!(EINVAL, "the address is empty.");
+        }
+        // The null terminator of the path is counted
+        if s.len() + 1 > MAX_PATH_LEN {
+            return_errno!(EINVAL, "the address is too long.");
+        }
+        Ok(Self::File(None, UnixPath::new(s)))
+    }
+}
+
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct UnixPath {
     inner: PathBuf,
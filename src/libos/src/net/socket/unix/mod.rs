@@ -13,10 +13,12 @@ pub fn unix_socket(socket_type: SocketType, flags: FileFlags, protocol: i32) ->
         return_errno!(EPROTONOSUPPORT, "protocol is not supported");
     }
 
-    if socket_type == SocketType::STREAM {
-        Ok(Stream::new(flags))
-    } else {
-        return_errno!(ESOCKTNOSUPPORT, "only stream type is supported");
+    match socket_type {
+        SocketType::STREAM | SocketType::SEQPACKET => Ok(Stream::new(socket_type, flags)),
+        _ => return_errno!(
+            ESOCKTNOSUPPORT,
+            "only stream and seqpacket types are supported"
+        ),
     }
 }
 
@@ -29,10 +31,12 @@ pub fn socketpair(
         return_errno!(EPROTONOSUPPORT, "protocol is not supported");
     }
 
-    if socket_type == SocketType::STREAM {
-        Stream::socketpair(flags)
-    } else {
-        return_errno!(ESOCKTNOSUPPORT, "only stream type is supported");
+    match socket_type {
+        SocketType::STREAM | SocketType::SEQPACKET => Stream::socketpair(socket_type, flags),
+        _ => return_errno!(
+            ESOCKTNOSUPPORT,
+            "only stream and seqpacket types are supported"
+        ),
     }
 }
 
@@ -11,7 +11,7 @@ lazy_static! {
 #[derive(PartialEq, Eq, PartialOrd, Ord)]
 pub enum AddressSpaceKey {
     FileKey(usize),
-    AbstrKey(String),
+    AbstrKey(Vec<u8>),
 }
 
 impl AddressSpaceKey {
@@ -19,8 +19,8 @@ impl AddressSpaceKey {
         AddressSpaceKey::FileKey(inode)
     }
 
-    pub fn from_path(path: String) -> Self {
-        AddressSpaceKey::AbstrKey(path)
+    pub fn from_abstract_name(name: Vec<u8>) -> Self {
+        AddressSpaceKey::AbstrKey(name)
     }
 }
 
@@ -54,6 +54,7 @@ impl AddressSpace {
         &self,
         addr: &Addr,
         capacity: usize,
+        socket_type: SocketType,
         nonblocking: bool,
         notifier: Arc<RelayNotifier>,
     ) -> Result<()> {
@@ -64,7 +65,12 @@ impl AddressSpace {
             if option.is_none() {
                 space.insert(
                     key,
-                    Some(Arc::new(Listener::new(capacity, nonblocking, notifier)?)),
+                    Some(Arc::new(Listener::new(
+                        capacity,
+                        socket_type,
+                        nonblocking,
+                        notifier,
+                    )?)),
                 );
                 Ok(())
             } else {
@@ -153,7 +159,7 @@ impl AddressSpace {
                     None
                 }
             }
-            Addr::Abstract(path) => Some(AddressSpaceKey::from_path(addr.path_str().to_string())),
+            Addr::Abstract(name) => Some(AddressSpaceKey::from_abstract_name(name.clone())),
         }
     }
 }
@@ -0,0 +1,120 @@
+use super::*;
+use net::socket::{CMessages, CmsgData};
+use std::{mem, ptr};
+
+/// A control message sent along with the data of a unix stream socket.
+///
+/// The control message is parsed at the sending end and queued at the receiving
+/// end. File descriptors are resolved to files at the time of sending, so that
+/// the receiver is not affected by the sender closing the file descriptors
+/// afterwards. The files that are never received are closed when the control
+/// message is dropped.
+///
+/// `SCM_CREDENTIALS` is queued like the others, but only received by the
+/// receiver that enables `SO_PASSCRED`.
+pub enum ControlMessage {
+    ScmRights(Vec<FileRef>),
+    ScmCredentials(libc::ucred),
+}
+
+impl ControlMessage {
+    /// Parse the control messages given by the sender.
+    pub fn from_bytes(msg_control: &[u8]) -> Result<Vec<Self>> {
+        let mut msg_control = msg_control.to_vec();
+        let mut control_msgs = Vec::new();
+        for cmsg in CMessages::from_bytes(&mut msg_control) {
+            match cmsg {
+                CmsgData::ScmRights(scm_rights) => {
+                    let current = current!();
+                    let files = scm_rights
+                        .iter_fds()
+                        .map(|fd| current.file(fd))
+                        .collect::<Result<Vec<FileRef>>>()?;
+                    control_msgs.push(ControlMessage::ScmRights(files));
+                }
+                CmsgData::ScmCredentials(ucred) => {
+                    // Occlum has no privileged processes, so the credentials
+                    // must be those of the sender itself
+                    let pid = current!().process().pid();
+                    if ucred.pid as pid_t != pid || ucred.uid != 0 || ucred.gid != 0 {
+                        return_errno!(EPERM, "the credentials are not the sender's");
+                    }
+                    control_msgs.push(ControlMessage::ScmCredentials(ucred));
+                }
+            }
+        }
+        Ok(control_msgs)
+    }
+
+    /// Write the control message to the receiver's buffer.
+    ///
+    /// For `SCM_RIGHTS`, the files are installed in the file table of the
+    /// current process. If the buffer is too small to hold all the file
+    /// descriptors, the rest of the files are closed.
+    ///
+    /// Returns the number of bytes written and whether the control message
+    /// is truncated.
+    pub fn write_to(self, buf: &mut [u8], flags: RecvFlags) -> (usize, bool) {
+        match self {
+            ControlMessage::ScmRights(files) => {
+                let hdr_len = unsafe { libc::CMSG_LEN(0) } as usize;
+                if buf.len() < hdr_len {
+                    return (0, !files.is_empty());
+                }
+
+                let max_fds = (buf.len() - hdr_len) / mem::size_of::<FileDesc>();
+                let num_fds = max_fds.min(files.len());
+                let is_truncated = num_fds < files.len();
+
+                let close_on_spawn = flags.contains(RecvFlags::MSG_CMSG_CLOEXEC);
+                let current = current!();
+                let data = &mut buf[hdr_len..];
+                for (file, fd_bytes) in files
+                    .into_iter()
+                    .take(num_fds)
+                    .zip(data.chunks_exact_mut(mem::size_of::<FileDesc>()))
+                {
+                    let fd = current.add_file(file, close_on_spawn);
+                    fd_bytes.copy_from_slice(&fd.to_ne_bytes());
+                }
+
+                let cmsg_len =
+                    unsafe { libc::CMSG_LEN((num_fds * mem::size_of::<FileDesc>()) as u32) };
+                let cmsg = libc::cmsghdr {
+                    cmsg_len: cmsg_len as _,
+                    cmsg_level: libc::SOL_SOCKET,
+                    cmsg_type: libc::SCM_RIGHTS,
+                };
+                unsafe {
+                    ptr::write_unaligned(buf.as_mut_ptr() as *mut libc::cmsghdr, cmsg);
+                }
+
+                let cmsg_space =
+                    unsafe { libc::CMSG_SPACE((num_fds * mem::size_of::<FileDesc>()) as u32) };
+                let written_len = (cmsg_space as usize).min(buf.len());
+                (written_len, is_truncated)
+            }
+            ControlMessage::ScmCredentials(ucred) => {
+                let data_len = mem::size_of::<libc::ucred>();
+                let cmsg_len = unsafe { libc::CMSG_LEN(data_len as u32) } as usize;
+                if buf.len() < cmsg_len {
+                    return (0, true);
+                }
+
+                let cmsg = libc::cmsghdr {
+                    cmsg_len: cmsg_len as _,
+                    cmsg_level: libc::SOL_SOCKET,
+                    cmsg_type: libc::SCM_CREDENTIALS,
+                };
+                let hdr_len = unsafe { libc::CMSG_LEN(0) } as usize;
+                unsafe {
+                    ptr::write_unaligned(buf.as_mut_ptr() as *mut libc::cmsghdr, cmsg);
+                    ptr::write_unaligned(buf[hdr_len..].as_mut_ptr() as *mut libc::ucred, ucred);
+                }
+
+                let cmsg_space = unsafe { libc::CMSG_SPACE(data_len as u32) } as usize;
+                (cmsg_space.min(buf.len()), false)
+            }
+        }
+    }
+}
@@ -1,30 +1,50 @@
+use super::control::ControlMessage;
 use super::*;
 use events::{Event, EventFilter, Notifier, Observer};
 use fs::channel::{Channel, Consumer, Producer};
 use fs::{IoEvents, IoNotifier};
 use std::any::Any;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Weak};
+use std::time::Duration;
 
 pub type Endpoint = Arc<Inner>;
 
 /// Constructor of two connected Endpoints
-pub fn end_pair(nonblocking: bool) -> Result<(Endpoint, Endpoint)> {
-    let (pro_a, con_a) = Channel::new(DEFAULT_BUF_SIZE)?.split();
-    let (pro_b, con_b) = Channel::new(DEFAULT_BUF_SIZE)?.split();
+///
+/// The buffer sizes are those of the first endpoint, which decide the
+/// capacities of the channels in both directions. The socket type is either
+/// `SOCK_STREAM` or `SOCK_SEQPACKET`.
+pub fn end_pair(
+    socket_type: SocketType,
+    nonblocking: bool,
+    recv_buf_size: usize,
+    send_buf_size: usize,
+) -> Result<(Endpoint, Endpoint)> {
+    let (pro_a, con_a) = Channel::new(recv_buf_size)?.split();
+    let (pro_b, con_b) = Channel::new(send_buf_size)?.split();
 
     let mut end_a = Arc::new(Inner {
+        socket_type,
         addr: RwLock::new(None),
         reader: con_a,
         writer: pro_b,
         peer: Weak::default(),
         ancillary: RwLock::new(None),
+        control_msgs: SgxMutex::new(VecDeque::new()),
+        passcred: AtomicBool::new(false),
+        relay_notifier: RwLock::new(Weak::new()),
     });
     let end_b = Arc::new(Inner {
+        socket_type,
         addr: RwLock::new(None),
         reader: con_b,
         writer: pro_a,
         peer: Arc::downgrade(&end_a),
         ancillary: RwLock::new(None),
+        control_msgs: SgxMutex::new(VecDeque::new()),
+        passcred: AtomicBool::new(false),
+        relay_notifier: RwLock::new(Weak::new()),
     });
 
     unsafe {
@@ -39,14 +59,27 @@ pub fn end_pair(nonblocking: bool) -> Result<(Endpoint, Endpoint)> {
 
 /// One end of the connected unix socket
 pub struct Inner {
+    // Data of SOCK_SEQPACKET is transferred in messages instead of a byte stream
+    socket_type: SocketType,
     addr: RwLock<Option<Addr>>,
     reader: Consumer<u8>,
     writer: Producer<u8>,
     peer: Weak<Self>,
     ancillary: RwLock<Option<Ancillary>>,
+    // Control messages sent by the peer and not received yet, in the order of
+    // the data sent along with them
+    control_msgs: SgxMutex<VecDeque<PendingControl>>,
+    // Whether the socket that owns the endpoint enables SO_PASSCRED
+    passcred: AtomicBool,
+    // The notifier of the socket that owns the endpoint
+    relay_notifier: RwLock<Weak<RelayNotifier>>,
 }
 
 impl Inner {
+    pub fn socket_type(&self) -> SocketType {
+        self.socket_type
+    }
+
     pub fn addr(&self) -> Option<Addr> {
         self.addr.read().unwrap().clone()
     }
@@ -70,37 +103,146 @@ impl Inner {
         assert_eq!(cons_nonblocking, prod_nonblocking);
         cons_nonblocking
     }
+
+    pub fn recv_timeout(&self) -> Option<Duration> {
+        self.reader.timeout()
+    }
+
+    pub fn set_recv_timeout(&self, timeout: Option<Duration>) {
+        self.reader.set_timeout(timeout);
+    }
+
+    pub fn send_timeout(&self) -> Option<Duration> {
+        self.writer.timeout()
+    }
+
+    pub fn set_send_timeout(&self, timeout: Option<Duration>) {
+        self.writer.set_timeout(timeout);
+    }
+
+    pub fn recv_buf_size(&self) -> usize {
+        self.reader.capacity()
+    }
+
+    pub fn send_buf_size(&self) -> usize {
+        self.writer.capacity()
+    }
+
+    /// Change the buffer size for receiving, keeping the data not received yet.
+    pub fn set_recv_buf_size(&self, size: usize) -> Result<()> {
+        match self.peer.upgrade() {
+            Some(peer) => peer.writer.resize(&self.reader, size),
+            // No more data can be received once the peer is closed
+            None => Ok(()),
+        }
+    }
+
+    /// Change the buffer size for sending, keeping the data not received yet
+    /// by the peer.
+    pub fn set_send_buf_size(&self, size: usize) -> Result<()> {
+        match self.peer.upgrade() {
+            Some(peer) => self.writer.resize(&peer.reader, size),
+            // No more data can be sent once the peer is closed
+            None => Ok(()),
+        }
+    }
+
     pub fn read(&self, buf: &mut [u8]) -> Result<usize> {
-        self.reader.pop_slice(buf)
+        self.readv(&mut [buf], false)
     }
 
-    pub fn write(&self, buf: &[u8]) -> Result<usize> {
-        self.writer.push_slice(buf)
+    /// Read into the buffers.
+    ///
+    /// If `dontwait` is true, the read never blocks, regardless of the
+    /// nonblocking mode. This is also the case for `peekv` and
+    /// `writev_with_control`.
+    ///
+    /// Like Linux, the read stops at the end of the data sent along with
+    /// control messages, so that the data sent after them is never received
+    /// together with the control messages.
+    pub fn readv(&self, bufs: &mut [&mut [u8]], dontwait: bool) -> Result<usize> {
+        if self.socket_type == SocketType::SEQPACKET {
+            return self.recv_msg(bufs, false, dontwait).map(|(len, _)| len);
+        }
+
+        match self.control_read_limit() {
+            Some(limit) => self.do_readv(&mut limit_bufs(bufs, limit), dontwait),
+            None => self.do_readv(bufs, dontwait),
+        }
     }
 
-    pub fn readv(&self, bufs: &mut [&mut [u8]]) -> Result<usize> {
-        self.reader.pop_slices(bufs)
+    fn do_readv(&self, bufs: &mut [&mut [u8]], dontwait: bool) -> Result<usize> {
+        if dontwait {
+            self.reader.try_pop_slices(bufs)
+        } else {
+            self.reader.pop_slices(bufs)
+        }
+    }
+
+    pub fn peekv(&self, bufs: &mut [&mut [u8]], dontwait: bool) -> Result<usize> {
+        if self.socket_type == SocketType::SEQPACKET {
+            return self.recv_msg(bufs, true, dontwait).map(|(len, _)| len);
+        }
+
+        match self.control_read_limit() {
+            Some(limit) => self.do_peekv(&mut limit_bufs(bufs, limit), dontwait),
+            None => self.do_peekv(bufs, dontwait),
+        }
     }
 
-    pub fn writev(&self, bufs: &[&[u8]]) -> Result<usize> {
-        self.writer.push_slices(bufs)
+    fn do_peekv(&self, bufs: &mut [&mut [u8]], dontwait: bool) -> Result<usize> {
+        if dontwait {
+            self.reader.try_peek_slices(bufs)
+        } else {
+            self.reader.peek_slices(bufs)
+        }
+    }
+
+    /// Receive a single message of SOCK_SEQPACKET into the buffers.
+    ///
+    /// Returns the length of the received data and that of the message. The
+    /// part of the message that does not fit in the buffers is discarded.
+    pub fn recv_msg(
+        &self,
+        bufs: &mut [&mut [u8]],
+        peek: bool,
+        dontwait: bool,
+    ) -> Result<(usize, usize)> {
+        debug_assert!(self.socket_type == SocketType::SEQPACKET);
+        self.reader.pop_msg(bufs, peek, dontwait)
     }
 
     pub fn bytes_to_read(&self) -> usize {
         self.reader.items_to_consume()
     }
 
-    pub fn shutdown(&self, how: HowToShut) -> Result<()> {
-        if !self.is_connected() {
-            return_errno!(ENOTCONN, "The socket is not connected.");
-        }
+    /// Returns the number of bytes sent but not read by the peer yet.
+    pub fn bytes_unread_by_peer(&self) -> usize {
+        self.writer.items_to_consume()
+    }
 
-        if how.to_shut_read() {
-            self.reader.shutdown()
-        }
+    /// Shut down the endpoint.
+    ///
+    /// Shutting down both directions notifies the peer twice, so the events
+    /// are coalesced to wake the observers of the peer socket only once.
+    pub fn shutdown(&self, how: HowToShut) -> Result<()> {
+        let peer = self
+            .peer
+            .upgrade()
+            .ok_or_else(|| errno!(ENOTCONN, "The socket is not connected."))?;
+
+        let shutdown = || {
+            if how.to_shut_read() {
+                self.reader.shutdown()
+            }
 
-        if how.to_shut_write() {
-            self.writer.shutdown()
+            if how.to_shut_write() {
+                self.writer.shutdown()
+            }
+        };
+        match peer.relay_notifier.read().unwrap().upgrade() {
+            Some(relay_notifier) => relay_notifier.coalesce(shutdown),
+            None => shutdown(),
         }
 
         Ok(())
@@ -134,7 +276,133 @@ impl Inner {
         self.peer.upgrade().map(|end| end.ancillary()).flatten()
     }
 
+    pub fn set_passcred(&self, passcred: bool) {
+        self.passcred.store(passcred, Ordering::Relaxed);
+    }
+
+    /// Returns whether the credentials of the sender are sent along with the
+    /// data, which is the case if either end enables `SO_PASSCRED`.
+    fn passes_credentials(&self) -> bool {
+        self.passcred.load(Ordering::Relaxed)
+            || self
+                .peer
+                .upgrade()
+                .map_or(false, |peer| peer.passcred.load(Ordering::Relaxed))
+    }
+
+    /// Write data along with control messages to the peer.
+    ///
+    /// The data is written first, and then the control messages are queued at
+    /// the peer with the offset of the data, so no lock is held while waiting
+    /// for the buffer space. The control messages are received along with the
+    /// first read of the data. If the data is read before the control messages
+    /// are queued, they are received by the next `recvmsg` instead. For
+    /// SOCK_SEQPACKET, the data is written as a single message.
+    ///
+    /// Like Linux, the credentials of the sender are attached to the data if
+    /// either end enables `SO_PASSCRED` and the sender gives none. The data of
+    /// a stream sent in a row with the same credentials is received together.
+    pub fn writev_with_control(
+        &self,
+        bufs: &[&[u8]],
+        mut control_msgs: Vec<ControlMessage>,
+        dontwait: bool,
+    ) -> Result<usize> {
+        let has_credentials = control_msgs
+            .iter()
+            .any(|msg| matches!(msg, ControlMessage::ScmCredentials(_)));
+        if !has_credentials && self.passes_credentials() {
+            let ucred = Ancillary::from_current().ucred();
+            control_msgs.push(ControlMessage::ScmCredentials(ucred));
+        }
+
+        if control_msgs.is_empty() {
+            return if self.socket_type == SocketType::SEQPACKET {
+                self.writer.push_msg(bufs, dontwait)
+            } else if dontwait {
+                self.writer.try_push_slices(bufs)
+            } else {
+                self.writer.push_slices(bufs)
+            };
+        }
+
+        let peer = self
+            .peer
+            .upgrade()
+            .ok_or_else(|| errno!(EPIPE, "the peer has been closed"))?;
+        let (offset, data_len) = if self.socket_type == SocketType::SEQPACKET {
+            self.writer.push_msg_with_offset(bufs, dontwait)?
+        } else {
+            self.writer.push_slices_with_offset(bufs, dontwait)?
+        };
+
+        // Concurrent writers may queue their control messages out of order
+        let mut peer_control_msgs = peer.control_msgs.lock().unwrap();
+        let idx = peer_control_msgs
+            .iter()
+            .rposition(|pending| pending.offset < offset)
+            .map_or(0, |idx| idx + 1);
+        // Extend the data of the previous credentials instead, if they are the
+        // same and the data is contiguous
+        if self.socket_type == SocketType::STREAM && idx > 0 {
+            let prev = &mut peer_control_msgs[idx - 1];
+            if prev.offset + prev.len == offset && prev.has_same_credentials(&control_msgs) {
+                prev.len += data_len;
+                return Ok(data_len);
+            }
+        }
+        peer_control_msgs.insert(
+            idx,
+            PendingControl {
+                offset,
+                len: data_len,
+                msgs: control_msgs,
+            },
+        );
+        Ok(data_len)
+    }
+
+    /// Pop the control messages sent along with the data that has been read.
+    pub fn pop_control_msgs(&self) -> Vec<ControlMessage> {
+        let read_offset = self.reader.offset();
+        let mut control_msgs = self.control_msgs.lock().unwrap();
+        let mut msgs = Vec::new();
+        while let Some(pending) = control_msgs.front() {
+            if pending.offset >= read_offset {
+                break;
+            }
+            msgs.extend(control_msgs.pop_front().unwrap().msgs);
+        }
+        msgs
+    }
+
+    /// Returns whether there are control messages sent along with the data
+    /// that has been read.
+    pub fn has_control_msgs_to_pop(&self) -> bool {
+        let read_offset = self.reader.offset();
+        self.control_msgs
+            .lock()
+            .unwrap()
+            .front()
+            .map_or(false, |pending| pending.offset < read_offset)
+    }
+
+    /// Returns the number of bytes that can be read without going past the
+    /// end of the data sent along with control messages.
+    fn control_read_limit(&self) -> Option<usize> {
+        let read_offset = self.reader.offset();
+        self.control_msgs
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|pending| pending.offset + pending.len)
+            .find(|end_offset| *end_offset > read_offset)
+            .map(|end_offset| end_offset - read_offset)
+    }
+
     pub(self) fn register_relay_notifier(&self, observer: &Arc<RelayNotifier>) {
+        *self.relay_notifier.write().unwrap() = Arc::downgrade(observer);
+
         self.reader.notifier().register(
             Arc::downgrade(observer) as Weak<dyn Observer<_>>,
             None,
@@ -147,40 +415,118 @@ impl Inner {
             None,
         );
     }
+}
+
+/// Control messages queued at the receiving end, along with the offset and
+/// length of the data sent with them.
+struct PendingControl {
+    offset: usize,
+    len: usize,
+    msgs: Vec<ControlMessage>,
+}
+
+impl PendingControl {
+    // Whether both have only the credentials, which are the same
+    fn has_same_credentials(&self, msgs: &[ControlMessage]) -> bool {
+        match (self.msgs.as_slice(), msgs) {
+            ([ControlMessage::ScmCredentials(ucred)], [ControlMessage::ScmCredentials(other)]) => {
+                ucred.pid == other.pid && ucred.uid == other.uid && ucred.gid == other.gid
+            }
+            _ => false,
+        }
+    }
+}
 
-    fn is_connected(&self) -> bool {
-        self.peer.upgrade().is_some()
+/// Returns the buffers truncated to hold no more than `limit` bytes in total.
+fn limit_bufs<'a>(bufs: &'a mut [&mut [u8]], mut limit: usize) -> Vec<&'a mut [u8]> {
+    let mut limited_bufs = Vec::new();
+    for buf in bufs.iter_mut() {
+        if limit == 0 {
+            break;
+        }
+        let len = buf.len().min(limit);
+        limited_bufs.push(&mut buf[..len]);
+        limit -= len;
     }
+    limited_bufs
 }
 
 /// Ancillary data of connected unix socket's sent/received control message.
+///
+/// It also records the credentials of the process that owns the endpoint,
+/// which can be retrieved by the peer with `SO_PEERCRED`.
 #[derive(Clone, Debug)]
 pub struct Ancillary {
     pub(super) tid: pid_t, // currently store tid to locate file table
+    pid: pid_t,
+    uid: uid_t,
+    gid: u32,
 }
 
 impl Ancillary {
+    /// Create the ancillary data of the current thread.
+    pub fn from_current() -> Self {
+        let current = current!();
+        Self {
+            tid: current.tid(),
+            pid: current.process().pid(),
+            // Occlum does not support users and groups yet
+            uid: 0,
+            gid: 0,
+        }
+    }
+
     pub fn tid(&self) -> pid_t {
         self.tid
     }
+
+    pub fn ucred(&self) -> libc::ucred {
+        libc::ucred {
+            pid: self.pid as _,
+            uid: self.uid as _,
+            gid: self.gid as _,
+        }
+    }
 }
 
-// TODO: Add SO_SNDBUF and SO_RCVBUF to set/getsockopt to dynamcally change the size.
 // This value is got from /proc/sys/net/core/rmem_max and wmem_max that are same on linux.
 pub const DEFAULT_BUF_SIZE: usize = 208 * 1024;
+// The minimal buffer size, which is the same as SOCK_MIN_SNDBUF on linux.
+const MIN_BUF_SIZE: usize = 4608;
+
+/// Get the actual buffer size from the one set by `SO_SNDBUF` or `SO_RCVBUF`.
+///
+/// Like Linux, the size is capped by `DEFAULT_BUF_SIZE` and then doubled to
+/// leave room for bookkeeping, and the doubled size is reported back by
+/// getsockopt. We do not need the bookkeeping overhead, so a larger buffer
+/// size actually allows more data to be buffered.
+pub fn buf_size_from_user(size: usize) -> usize {
+    (size.min(DEFAULT_BUF_SIZE) * 2).max(MIN_BUF_SIZE)
+}
 
 /// An observer used to observe both reader and writer of the endpoint. It also contains a
 /// notifier that relays the notification of the endpoint.
 pub(super) struct RelayNotifier {
     notifier: IoNotifier,
-    endpoint: SgxMutex<Option<Endpoint>>,
+    inner: SgxMutex<RelayInner>,
+}
+
+struct RelayInner {
+    endpoint: Option<Endpoint>,
+    // The events held back while coalescing, which are relayed at once later
+    coalesce_depth: usize,
+    pending: IoEvents,
 }
 
 impl RelayNotifier {
     pub fn new() -> Self {
         let notifier = IoNotifier::new();
-        let endpoint = SgxMutex::new(None);
-        Self { notifier, endpoint }
+        let inner = SgxMutex::new(RelayInner {
+            endpoint: None,
+            coalesce_depth: 0,
+            pending: IoEvents::empty(),
+        });
+        Self { notifier, inner }
     }
 
     pub fn notifier(&self) -> &IoNotifier {
@@ -189,30 +535,76 @@ impl RelayNotifier {
 
     pub fn observe_endpoint(self: &Arc<Self>, endpoint: &Endpoint) {
         endpoint.register_relay_notifier(self);
-        *self.endpoint.lock().unwrap() = Some(endpoint.clone());
+        self.inner.lock().unwrap().endpoint = Some(endpoint.clone());
+    }
+
+    /// Run `f` with the events relayed in it coalesced.
+    ///
+    /// The events relayed during `f` are merged and broadcast once after `f`
+    /// returns, so several readiness changes in a row, e.g., by shutting down
+    /// both directions, wake the observers of the socket only once. The calls
+    /// can be nested, in which case the events are broadcast when the
+    /// outermost call returns. The events relayed by other threads during `f`
+    /// are also held back, so `f` should be short and never block.
+    pub fn coalesce<R>(&self, f: impl FnOnce() -> R) -> R {
+        self.inner.lock().unwrap().coalesce_depth += 1;
+        let _guard = CoalesceGuard {
+            relay_notifier: self,
+        };
+        f()
+    }
+
+    fn end_coalesce(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.coalesce_depth -= 1;
+        if inner.coalesce_depth > 0 || inner.pending.is_empty() {
+            return;
+        }
+        let event = std::mem::replace(&mut inner.pending, IoEvents::empty());
+        // A notifier can only have events after observe_endpoint
+        self.notifier()
+            .broadcast(&(inner.endpoint.as_ref().unwrap().poll() & event));
+    }
+}
+
+// Ends the coalescing of the relay notifier when dropped, even on panics
+struct CoalesceGuard<'a> {
+    relay_notifier: &'a RelayNotifier,
+}
+
+impl<'a> Drop for CoalesceGuard<'a> {
+    fn drop(&mut self) {
+        self.relay_notifier.end_coalesce();
     }
 }
 
 impl Observer<IoEvents> for RelayNotifier {
     fn on_event(&self, event: &IoEvents, _metadata: &Option<Weak<dyn Any + Send + Sync>>) {
-        let endpoint = self.endpoint.lock().unwrap();
+        let mut inner = self.inner.lock().unwrap();
         // Only endpoint can broadcast events
 
         let mut event = event.clone();
         // The event of the channel should not be broadcasted directly to socket.
         // The event transformation should be consistant with poll.
+        // Like poll, a half-closed stream is also readable and a fully-closed stream is also
+        // writable, so that the waiters of IN or OUT are woken up by these events, too.
         if event.contains(IoEvents::HUP) {
             event -= IoEvents::HUP;
-            event |= IoEvents::RDHUP;
+            event |= IoEvents::RDHUP | IoEvents::IN;
         }
 
         if event.contains(IoEvents::ERR) {
             event -= IoEvents::ERR;
-            event |= IoEvents::HUP;
+            event |= IoEvents::HUP | IoEvents::OUT;
+        }
+
+        if inner.coalesce_depth > 0 {
+            inner.pending |= event;
+            return;
         }
 
         // A notifier can only have events after observe_endpoint
         self.notifier()
-            .broadcast(&(endpoint.as_ref().unwrap().poll() & event));
+            .broadcast(&(inner.endpoint.as_ref().unwrap().poll() & event));
     }
 }
@@ -18,11 +18,7 @@ impl File for Stream {
     }
 
     fn write(&self, buf: &[u8]) -> Result<usize> {
-        let status = (*self.inner()).clone();
-        match status {
-            Status::Connected(endpoint) => endpoint.write(buf),
-            _ => return_errno!(ENOTCONN, "unconnected socket"),
-        }
+        self.send_slices(&[buf], Vec::new(), SendFlags::empty())
     }
 
     fn read_at(&self, offset: usize, buf: &mut [u8]) -> Result<usize> {
@@ -42,17 +38,13 @@ impl File for Stream {
     fn readv(&self, bufs: &mut [&mut [u8]]) -> Result<usize> {
         let status = (*self.inner()).clone();
         match status {
-            Status::Connected(endpoint) => endpoint.readv(bufs),
+            Status::Connected(endpoint) => endpoint.readv(bufs, false),
             _ => return_errno!(ENOTCONN, "unconnected socket"),
         }
     }
 
     fn writev(&self, bufs: &[&[u8]]) -> Result<usize> {
-        let status = (*self.inner()).clone();
-        match status {
-            Status::Connected(endpoint) => endpoint.writev(bufs),
-            _ => return_errno!(ENOTCONN, "unconnected socket"),
-        }
+        self.send_slices(bufs, Vec::new(), SendFlags::empty())
     }
 
     fn ioctl(&self, cmd: &mut IoctlCmd) -> Result<i32> {
@@ -69,6 +61,13 @@ impl File for Stream {
                 }
                 _ => return_errno!(ENOTCONN, "unconnected socket"),
             },
+            IoctlCmd::TIOCOUTQ(arg) => match &*self.inner() {
+                Status::Connected(endpoint) => {
+                    let bytes = endpoint.bytes_unread_by_peer();
+                    **arg = bytes.min(std::i32::MAX as usize) as i32;
+                }
+                _ => return_errno!(ENOTCONN, "unconnected socket"),
+            },
             _ => return_errno!(EINVAL, "unknown ioctl cmd for unix socket"),
         }
         Ok(0)
@@ -1,6 +1,7 @@
 use super::*;
 
 mod address_space;
+mod control;
 mod endpoint;
 mod file;
 mod stream;
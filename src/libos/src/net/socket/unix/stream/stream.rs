@@ -1,52 +1,75 @@
 use super::address_space::ADDRESS_SPACE;
-use super::endpoint::{end_pair, Ancillary, Endpoint, RelayNotifier};
+use super::control::ControlMessage;
+use super::endpoint::{
+    buf_size_from_user, end_pair, Ancillary, Endpoint, RelayNotifier, DEFAULT_BUF_SIZE,
+};
 use super::*;
 use events::{Event, EventFilter, Notifier, Observer};
 use fs::channel::Channel;
 use fs::IoEvents;
 use fs::{CreationFlags, FileMode};
-use net::socket::{CMessages, CmsgData, Iovs, MsgHdr, MsgHdrMut};
+use net::socket::{Iovs, MsgHdr, MsgHdrMut};
 use std::fmt;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
+
+// The default value of /proc/sys/net/core/somaxconn on Linux
+const SOMAXCONN: usize = 4096;
 
 /// SOCK_STREAM Unix socket. It has three statuses: unconnected, listening and connected.  When a
 /// socket is created, it is in unconnected status.  It will transfer to listening after listen is
 /// called and connected after connect is called. A socket in connected status can be obtained
 /// through a listening socket calling accept. Listening and connected are ultimate statuses. They
 /// will not transfer to other statuses.
+///
+/// SOCK_SEQPACKET Unix socket is also implemented by this type. It is connection-oriented, too,
+/// but preserves the message boundaries: each send is received by exactly one receive.
 pub struct Stream {
+    socket_type: SocketType,
     inner: SgxMutex<Status>,
+    // Whether the credentials of the peer are received along with the data, i.e., SO_PASSCRED
+    passcred: AtomicBool,
     // Use the internal notifier of RelayNotifier as the notifier of stream socket. It relays the
     // events of the endpoint, too.
     pub(super) notifier: Arc<RelayNotifier>,
 }
 
 impl Stream {
-    pub fn new(flags: FileFlags) -> Self {
+    pub fn new(socket_type: SocketType, flags: FileFlags) -> Self {
         Self {
+            socket_type,
             inner: SgxMutex::new(Status::Idle(Info::new(
                 flags.contains(FileFlags::SOCK_NONBLOCK),
             ))),
+            passcred: AtomicBool::new(false),
             notifier: Arc::new(RelayNotifier::new()),
         }
     }
 
-    pub fn socketpair(flags: FileFlags) -> Result<(Self, Self)> {
+    pub fn socketpair(socket_type: SocketType, flags: FileFlags) -> Result<(Self, Self)> {
         let nonblocking = flags.contains(FileFlags::SOCK_NONBLOCK);
-        let (end_a, end_b) = end_pair(nonblocking)?;
+        let (end_a, end_b) =
+            end_pair(socket_type, nonblocking, DEFAULT_BUF_SIZE, DEFAULT_BUF_SIZE)?;
         let notifier_a = Arc::new(RelayNotifier::new());
         let notifier_b = Arc::new(RelayNotifier::new());
         notifier_a.observe_endpoint(&end_a);
         notifier_b.observe_endpoint(&end_b);
+        // Both ends are owned by the creating process
+        end_a.set_ancillary(Ancillary::from_current());
+        end_b.set_ancillary(Ancillary::from_current());
 
         let socket_a = Self {
+            socket_type,
             inner: SgxMutex::new(Status::Connected(end_a)),
+            passcred: AtomicBool::new(false),
             notifier: notifier_a,
         };
 
         let socket_b = Self {
+            socket_type,
             inner: SgxMutex::new(Status::Connected(end_b)),
+            passcred: AtomicBool::new(false),
             notifier: notifier_b,
         };
 
@@ -110,12 +133,15 @@ impl Stream {
         Ok(())
     }
 
+    /// Listen for connections.
+    ///
+    /// Like Linux, the backlog is capped by `SOMAXCONN`, and one more
+    /// connection than the backlog can be queued.
     pub fn listen(&self, backlog: i32) -> Result<()> {
-        //TODO: restrict backlog accroding to /proc/sys/net/core/somaxconn
         if backlog < 0 {
             return_errno!(EINVAL, "negative backlog is not supported");
         }
-        let capacity = backlog as usize;
+        let capacity = (backlog as usize).min(SOMAXCONN) + 1;
 
         let mut inner = self.inner();
         match &*inner {
@@ -125,6 +151,7 @@ impl Stream {
                     ADDRESS_SPACE.add_listener(
                         addr,
                         capacity,
+                        self.socket_type,
                         info.nonblocking(),
                         self.notifier.clone(),
                     )?;
@@ -156,19 +183,37 @@ impl Stream {
                     }
                 }
 
-                let (end_self, end_incoming) = end_pair(info.nonblocking())?;
+                // Like Linux, a socket can only connect to a listener of the same type
+                if let Some(listener) = ADDRESS_SPACE.get_listener_ref(addr) {
+                    if listener.socket_type() != self.socket_type {
+                        return_errno!(EPROTOTYPE, "the socket types do not match");
+                    }
+                }
+
+                let (end_self, end_incoming) = end_pair(
+                    self.socket_type,
+                    info.nonblocking(),
+                    info.recv_buf_size(),
+                    info.send_buf_size(),
+                )?;
+                end_self.set_recv_timeout(info.recv_timeout());
+                end_self.set_send_timeout(info.send_timeout());
                 end_incoming.set_addr(addr);
                 if let Some(self_addr) = self_addr_opt {
                     end_self.set_addr(self_addr);
                 }
-                end_self.set_ancillary(Ancillary {
-                    tid: current!().tid(),
-                });
+                end_self.set_ancillary(Ancillary::from_current());
+                end_self.set_passcred(self.passcred.load(Ordering::Relaxed));
 
+                // Like Linux, a nonblocking socket fails with EAGAIN if the
+                // backlog is full. Unlike Linux, a blocking one fails with
+                // ECONNREFUSED instead of waiting.
                 ADDRESS_SPACE
                     .push_incoming(addr, end_incoming)
                     .map_err(|e| match e.errno() {
-                        Errno::EAGAIN => errno!(ECONNREFUSED, "the backlog is full"),
+                        Errno::EAGAIN if !info.nonblocking() => {
+                            errno!(ECONNREFUSED, "the backlog is full")
+                        }
                         _ => e,
                     })?;
 
@@ -193,9 +238,10 @@ impl Stream {
             Status::Listening(addr) => {
                 let endpoint = ADDRESS_SPACE.pop_incoming(&addr)?;
                 endpoint.set_nonblocking(flags.contains(FileFlags::SOCK_NONBLOCK));
-                endpoint.set_ancillary(Ancillary {
-                    tid: current!().tid(),
-                });
+                endpoint.set_ancillary(Ancillary::from_current());
+                // Like Linux, the accepted socket inherits SO_PASSCRED
+                let passcred = self.passcred.load(Ordering::Relaxed);
+                endpoint.set_passcred(passcred);
                 let notifier = Arc::new(RelayNotifier::new());
                 notifier.observe_endpoint(&endpoint);
 
@@ -205,7 +251,9 @@ impl Stream {
 
                 Ok((
                     Self {
+                        socket_type: self.socket_type,
                         inner: SgxMutex::new(Status::Connected(endpoint)),
+                        passcred: AtomicBool::new(passcred),
                         notifier: notifier,
                     },
                     peer_addr,
@@ -215,14 +263,12 @@ impl Stream {
         }
     }
 
-    // TODO: handle flags
     pub fn sendto(&self, buf: &[u8], flags: SendFlags, addr: &Option<Addr>) -> Result<usize> {
-        self.write(buf)
+        self.send_slices(&[buf], Vec::new(), flags)
     }
 
-    // TODO: handle flags
     pub fn recvfrom(&self, buf: &mut [u8], flags: RecvFlags) -> Result<(usize, Option<Addr>)> {
-        let data_len = self.read(buf)?;
+        let (data_len, _) = self.recv_slices(&mut [buf], flags)?;
         let addr = self.peer_addr().ok();
 
         debug!("recvfrom {:?}", addr);
@@ -231,53 +277,305 @@ impl Stream {
     }
 
     pub fn sendmsg(&self, msg_hdr: &MsgHdr, flags: SendFlags) -> Result<usize> {
-        if !flags.is_empty() {
-            warn!("unsupported flags: {:?}", flags);
-        }
+        // Parse the control messages before sending any data so that an
+        // invalid control message leaves nothing sent.
+        let control_msgs = match msg_hdr.get_control() {
+            Some(msg_control) => ControlMessage::from_bytes(msg_control)?,
+            None => Vec::new(),
+        };
 
         let bufs = msg_hdr.get_iovs().as_slices();
-        let mut data_len = self.writev(bufs)?;
+        self.send_slices(bufs, control_msgs, flags)
+    }
+
+    pub fn recvmsg(
+        &self,
+        msg_hdr: &mut MsgHdrMut,
+        flags: RecvFlags,
+    ) -> Result<(usize, MsgHdrFlags)> {
+        let bufs = msg_hdr.get_iovs_mut().as_slices_mut();
+        let (data_len, mut flags_recvd) = self.recv_slices(bufs, flags)?;
+
+        // For stream socket, the msg_name is ignored. And other fields are not supported.
+        msg_hdr.set_name_len(0);
+
+        // The control messages are left in the buffer when peeking
+        if flags.contains(RecvFlags::MSG_PEEK) {
+            msg_hdr.set_control_len(0)?;
+            return Ok((data_len, flags_recvd));
+        }
 
-        if let Some(msg_control) = msg_hdr.get_control() {
-            data_len += self.write(msg_control)?;
+        let mut control_msgs = match &*self.inner() {
+            Status::Connected(endpoint) => endpoint.pop_control_msgs(),
+            _ => Vec::new(),
+        };
+        // Like Linux, the credentials are received only with SO_PASSCRED, and
+        // at most once before other control messages
+        let mut ucred = None;
+        control_msgs.retain(|control_msg| match control_msg {
+            ControlMessage::ScmCredentials(msg_ucred) => {
+                ucred.get_or_insert(*msg_ucred);
+                false
+            }
+            _ => true,
+        });
+        if let Some(ucred) = ucred.filter(|_| self.passcred.load(Ordering::Relaxed)) {
+            control_msgs.insert(0, ControlMessage::ScmCredentials(ucred));
+        }
+
+        let mut control_len = 0;
+        for control_msg in control_msgs {
+            // The control message is discarded if there is no buffer for it
+            let is_truncated = match msg_hdr.get_control_mut() {
+                Some(msg_control) => {
+                    let (len, is_truncated) =
+                        control_msg.write_to(&mut msg_control[control_len..], flags);
+                    control_len += len;
+                    is_truncated
+                }
+                None => true,
+            };
+            if is_truncated {
+                flags_recvd |= MsgHdrFlags::MSG_CTRUNC;
+            }
+        }
+        msg_hdr.set_control_len(control_len)?;
+
+        Ok((data_len, flags_recvd))
+    }
+
+    pub fn setsockopt(&self, level: c_int, optname: c_int, optval: &[u8]) -> Result<()> {
+        match SockOptName::from_raw(level, optname) {
+            SockOptName::SO_ERROR => return_errno!(ENOPROTOOPT, "SO_ERROR is read-only"),
+            SockOptName::SO_TYPE
+            | SockOptName::SO_DOMAIN
+            | SockOptName::SO_PROTOCOL
+            | SockOptName::SO_ACCEPTCONN => {
+                return_errno!(ENOPROTOOPT, "the option is read-only")
+            }
+            SockOptName::SO_PASSCRED => {
+                let passcred = optval_to_int(optval)? != 0;
+                self.passcred.store(passcred, Ordering::Relaxed);
+                if let Status::Connected(endpoint) = &*self.inner() {
+                    endpoint.set_passcred(passcred);
+                }
+            }
+            SockOptName::SO_RCVTIMEO => self.set_recv_timeout(optval_to_timeout(optval)?),
+            SockOptName::SO_SNDTIMEO => self.set_send_timeout(optval_to_timeout(optval)?),
+            SockOptName::SO_RCVBUF => {
+                self.set_recv_buf_size(buf_size_from_user(optval_to_buf_size(optval)?))?
+            }
+            SockOptName::SO_SNDBUF => {
+                self.set_send_buf_size(buf_size_from_user(optval_to_buf_size(optval)?))?
+            }
+            SockOptName::IP_TOS
+            | SockOptName::IP_TTL
+            | SockOptName::IPV6_TCLASS
+            | SockOptName::IPV6_UNICAST_HOPS => {
+                return_errno!(EOPNOTSUPP, "IP options are not supported by unix socket")
+            }
+            SockOptName::TCP_NODELAY | SockOptName::TCP_CORK | SockOptName::TCP_INFO => {
+                return_errno!(EOPNOTSUPP, "TCP options are not supported by unix socket")
+            }
+            optname => warn!("setsockopt {:?} for unix socket is unimplemented", optname),
         }
-        Ok(data_len)
+        Ok(())
     }
 
-    pub fn recvmsg(&self, msg_hdr: &mut MsgHdrMut, flags: RecvFlags) -> Result<usize> {
-        if !flags.is_empty() {
-            warn!("unsupported flags: {:?}", flags);
+    pub fn getsockopt(&self, level: c_int, optname: c_int, optval: &mut [u8]) -> Result<usize> {
+        match SockOptName::from_raw(level, optname) {
+            SockOptName::SO_ERROR => {
+                // Both connect and data transfer of unix socket fail synchronously,
+                // so there is never a pending error
+                return Ok(int_to_optval(0, optval));
+            }
+            SockOptName::SO_TYPE => {
+                return Ok(int_to_optval(self.socket_type as c_int, optval));
+            }
+            SockOptName::SO_DOMAIN => {
+                return Ok(int_to_optval(libc::AF_UNIX, optval));
+            }
+            SockOptName::SO_PROTOCOL => {
+                // Unix socket has only the default protocol
+                return Ok(int_to_optval(0, optval));
+            }
+            SockOptName::SO_ACCEPTCONN => {
+                let is_listening = matches!(&*self.inner(), Status::Listening(_));
+                return Ok(int_to_optval(is_listening as c_int, optval));
+            }
+            SockOptName::SO_PEERCRED => {
+                let ucred = self.peer_ucred();
+                let src = unsafe {
+                    std::slice::from_raw_parts(
+                        &ucred as *const _ as *const u8,
+                        std::mem::size_of::<libc::ucred>(),
+                    )
+                };
+                let len = optval.len().min(src.len());
+                optval[..len].copy_from_slice(&src[..len]);
+                return Ok(len);
+            }
+            SockOptName::SO_PASSCRED => {
+                let passcred = self.passcred.load(Ordering::Relaxed);
+                return Ok(int_to_optval(passcred as c_int, optval));
+            }
+            SockOptName::SO_RCVTIMEO => {
+                return Ok(timeout_to_optval(self.recv_timeout(), optval));
+            }
+            SockOptName::SO_SNDTIMEO => {
+                return Ok(timeout_to_optval(self.send_timeout(), optval));
+            }
+            SockOptName::SO_RCVBUF => {
+                return Ok(int_to_optval(self.recv_buf_size() as c_int, optval));
+            }
+            SockOptName::SO_SNDBUF => {
+                return Ok(int_to_optval(self.send_buf_size() as c_int, optval));
+            }
+            SockOptName::IP_TOS
+            | SockOptName::IP_TTL
+            | SockOptName::IPV6_TCLASS
+            | SockOptName::IPV6_UNICAST_HOPS => {
+                return_errno!(EOPNOTSUPP, "IP options are not supported by unix socket")
+            }
+            SockOptName::TCP_NODELAY | SockOptName::TCP_CORK | SockOptName::TCP_INFO => {
+                return_errno!(EOPNOTSUPP, "TCP options are not supported by unix socket")
+            }
+            optname => warn!("getsockopt {:?} for unix socket is unimplemented", optname),
+        }
+        // Leave the option value untouched
+        Ok(optval.len())
+    }
+
+    /// Send the data along with the control messages.
+    ///
+    /// Like Linux, if the peer has been closed or shut down for reading, the
+    /// method fails with `EPIPE` and raises `SIGPIPE` unless `MSG_NOSIGNAL` is
+    /// given.
+    pub(super) fn send_slices(
+        &self,
+        bufs: &[&[u8]],
+        control_msgs: Vec<ControlMessage>,
+        flags: SendFlags,
+    ) -> Result<usize> {
+        // Like Linux, out-of-band data is not supported by unix socket
+        if flags.contains(SendFlags::MSG_OOB) {
+            return_errno!(EOPNOTSUPP, "MSG_OOB is not supported by unix socket");
+        }
+        // Like Linux, MSG_MORE is ignored as the data is never split into segments
+        let unsupported_flags =
+            flags - SendFlags::MSG_DONTWAIT - SendFlags::MSG_NOSIGNAL - SendFlags::MSG_MORE;
+        if !unsupported_flags.is_empty() {
+            warn!("unsupported flags: {:?}", unsupported_flags);
         }
 
-        let bufs = msg_hdr.get_iovs_mut().as_slices_mut();
-        let mut data_len = self.readv(bufs)?;
+        let status = (*self.inner()).clone();
+        let endpoint = match status {
+            Status::Connected(endpoint) => endpoint,
+            _ => return_errno!(ENOTCONN, "unconnected socket"),
+        };
 
-        // For stream socket, the msg_name is ignored. And other fields are not supported.
-        msg_hdr.set_name_len(0);
+        let dontwait = flags.contains(SendFlags::MSG_DONTWAIT);
+        let ret = endpoint.writev_with_control(bufs, control_msgs, dontwait);
+        if let Err(e) = &ret {
+            if e.errno() == EPIPE && !flags.contains(SendFlags::MSG_NOSIGNAL) {
+                crate::signal::do_tkill(current!().tid(), crate::signal::SIGPIPE.as_u8() as i32);
+            }
+        }
+        ret
+    }
+
+    /// Receive data into the buffers.
+    ///
+    /// For SOCK_SEQPACKET, a single message is received and `MSG_TRUNC` is
+    /// returned if the message does not fit in the buffers. Like Linux, the
+    /// length of the whole message is returned if `MSG_TRUNC` is given.
+    fn recv_slices(
+        &self,
+        bufs: &mut [&mut [u8]],
+        flags: RecvFlags,
+    ) -> Result<(usize, MsgHdrFlags)> {
+        if flags.contains(RecvFlags::MSG_OOB) {
+            return_errno!(EOPNOTSUPP, "MSG_OOB is not supported by unix socket");
+        }
+        let unsupported_flags = flags
+            - RecvFlags::MSG_PEEK
+            - RecvFlags::MSG_WAITALL
+            - RecvFlags::MSG_DONTWAIT
+            - RecvFlags::MSG_TRUNC
+            // Handled when the received file descriptors are installed
+            - RecvFlags::MSG_CMSG_CLOEXEC;
+        if !unsupported_flags.is_empty() {
+            warn!("unsupported flags: {:?}", unsupported_flags);
+        }
+
+        let status = (*self.inner()).clone();
+        let endpoint = match status {
+            Status::Connected(endpoint) => endpoint,
+            _ => return_errno!(ENOTCONN, "unconnected socket"),
+        };
 
-        if let Some(msg_control) = msg_hdr.get_control_mut() {
-            data_len += self.read(msg_control)?;
-
-            // For each control message that contains file descriptors (SOL_SOCKET and SCM_RIGHTS),
-            // reassign each fd in the message in receive end.
-            for cmsg in CMessages::from_bytes(msg_control) {
-                if let CmsgData::ScmRights(mut scm_rights) = cmsg {
-                    let send_tid = self.peer_ancillary().unwrap().tid();
-                    scm_rights.iter_and_reassign_fds(|send_fd| {
-                        let ipc_file = process::table::get_thread(send_tid)
-                            .unwrap()
-                            .files()
-                            .lock()
-                            .unwrap()
-                            .get(send_fd)
-                            .unwrap();
-                        current!().add_file(ipc_file.clone(), false)
-                    })
+        // MSG_DONTWAIT only affects this call, not the nonblocking mode
+        let dontwait = flags.contains(RecvFlags::MSG_DONTWAIT);
+        let peek = flags.contains(RecvFlags::MSG_PEEK);
+        if self.socket_type == SocketType::SEQPACKET {
+            // MSG_WAITALL is ignored since a message is always received as a whole
+            let (data_len, msg_len) = endpoint.recv_msg(bufs, peek, dontwait)?;
+            let flags_recvd = if data_len < msg_len {
+                MsgHdrFlags::MSG_TRUNC
+            } else {
+                MsgHdrFlags::empty()
+            };
+            let ret_len = if flags.contains(RecvFlags::MSG_TRUNC) {
+                msg_len
+            } else {
+                data_len
+            };
+            return Ok((ret_len, flags_recvd));
+        }
+
+        let data_len = if peek {
+            endpoint.peekv(bufs, dontwait)?
+        } else if flags.contains(RecvFlags::MSG_WAITALL) {
+            Self::readv_all(&endpoint, bufs, dontwait)?
+        } else {
+            endpoint.readv(bufs, dontwait)?
+        };
+        Ok((data_len, MsgHdrFlags::empty()))
+    }
+
+    /// Read until the buffers are full, the peer shuts down or an error occurs.
+    ///
+    /// Like Linux, if some data has been received, the data is returned instead
+    /// of the error, e.g., `EINTR` or `EAGAIN`.
+    fn readv_all(endpoint: &Endpoint, bufs: &mut [&mut [u8]], dontwait: bool) -> Result<usize> {
+        let total_len: usize = bufs.iter().map(|buf| buf.len()).sum();
+        let mut recv_len = 0;
+        while recv_len < total_len {
+            // Skip the part of the buffers that has been filled
+            let mut skip_len = recv_len;
+            let mut remain_bufs = Vec::new();
+            for buf in bufs.iter_mut() {
+                if skip_len >= buf.len() {
+                    skip_len -= buf.len();
+                    continue;
                 }
-                // Unix credentials need not to be handled here
+                remain_bufs.push(&mut buf[skip_len..]);
+                skip_len = 0;
+            }
+
+            match endpoint.readv(&mut remain_bufs, dontwait) {
+                Ok(0) => break,
+                Ok(len) => recv_len += len,
+                Err(_) if recv_len > 0 => break,
+                Err(e) => return Err(e),
+            }
+            // Like Linux, the data sent after control messages is never
+            // received together with them, even with MSG_WAITALL
+            if endpoint.has_control_msgs_to_pop() {
+                break;
             }
         }
-        Ok(data_len)
+        Ok(recv_len)
     }
 
     /// perform shutdown on the socket.
@@ -308,6 +606,74 @@ impl Stream {
         }
     }
 
+    fn recv_timeout(&self) -> Option<Duration> {
+        match &*self.inner() {
+            Status::Idle(info) => info.recv_timeout(),
+            Status::Connected(endpoint) => endpoint.recv_timeout(),
+            Status::Listening(_) => None,
+        }
+    }
+
+    fn set_recv_timeout(&self, timeout: Option<Duration>) {
+        match &mut *self.inner() {
+            Status::Idle(ref mut info) => info.set_recv_timeout(timeout),
+            Status::Connected(endpoint) => endpoint.set_recv_timeout(timeout),
+            Status::Listening(_) => warn!("timeout of listening unix socket is unsupported"),
+        }
+    }
+
+    fn send_timeout(&self) -> Option<Duration> {
+        match &*self.inner() {
+            Status::Idle(info) => info.send_timeout(),
+            Status::Connected(endpoint) => endpoint.send_timeout(),
+            Status::Listening(_) => None,
+        }
+    }
+
+    fn set_send_timeout(&self, timeout: Option<Duration>) {
+        match &mut *self.inner() {
+            Status::Idle(ref mut info) => info.set_send_timeout(timeout),
+            Status::Connected(endpoint) => endpoint.set_send_timeout(timeout),
+            Status::Listening(_) => warn!("timeout of listening unix socket is unsupported"),
+        }
+    }
+
+    fn recv_buf_size(&self) -> usize {
+        match &*self.inner() {
+            Status::Idle(info) => info.recv_buf_size(),
+            Status::Connected(endpoint) => endpoint.recv_buf_size(),
+            Status::Listening(_) => DEFAULT_BUF_SIZE,
+        }
+    }
+
+    // The buffers are allocated when the socket is connected. After that, the
+    // buffers are resized with the buffered data kept.
+    fn set_recv_buf_size(&self, size: usize) -> Result<()> {
+        match &mut *self.inner() {
+            Status::Idle(ref mut info) => info.set_recv_buf_size(size),
+            Status::Connected(endpoint) => endpoint.set_recv_buf_size(size)?,
+            Status::Listening(_) => warn!("buffer size of listening unix socket is unsupported"),
+        }
+        Ok(())
+    }
+
+    fn send_buf_size(&self) -> usize {
+        match &*self.inner() {
+            Status::Idle(info) => info.send_buf_size(),
+            Status::Connected(endpoint) => endpoint.send_buf_size(),
+            Status::Listening(_) => DEFAULT_BUF_SIZE,
+        }
+    }
+
+    fn set_send_buf_size(&self, size: usize) -> Result<()> {
+        match &mut *self.inner() {
+            Status::Idle(ref mut info) => info.set_send_buf_size(size),
+            Status::Connected(endpoint) => endpoint.set_send_buf_size(size)?,
+            Status::Listening(_) => warn!("buffer size of listening unix socket is unsupported"),
+        }
+        Ok(())
+    }
+
     pub(super) fn inner(&self) -> SgxMutexGuard<'_, Status> {
         self.inner.lock().unwrap()
     }
@@ -328,6 +694,18 @@ impl Stream {
         }
     }
 
+    /// The credentials of the peer, which are the same as Linux's invalid ones
+    /// if there is no peer.
+    fn peer_ucred(&self) -> libc::ucred {
+        self.peer_ancillary()
+            .map(|ancillary| ancillary.ucred())
+            .unwrap_or(libc::ucred {
+                pid: 0,
+                uid: u32::MAX,
+                gid: u32::MAX,
+            })
+    }
+
     fn set_ancillary(&self, ancillary: Ancillary) {
         if let Status::Connected(endpoint) = &*self.inner() {
             endpoint.set_ancillary(ancillary)
@@ -338,6 +716,7 @@ impl Stream {
 impl Debug for Stream {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.debug_struct("Stream")
+            .field("socket_type", &self.socket_type)
             .field("addr", &self.addr())
             .field("nonblocking", &self.nonblocking())
             .finish()
@@ -377,6 +756,10 @@ pub enum Status {
 pub struct Info {
     addr: Option<Addr>,
     nonblocking: bool,
+    recv_timeout: Option<Duration>,
+    send_timeout: Option<Duration>,
+    recv_buf_size: usize,
+    send_buf_size: usize,
 }
 
 impl Info {
@@ -384,6 +767,10 @@ impl Info {
         Self {
             addr: None,
             nonblocking: nonblocking,
+            recv_timeout: None,
+            send_timeout: None,
+            recv_buf_size: DEFAULT_BUF_SIZE,
+            send_buf_size: DEFAULT_BUF_SIZE,
         }
     }
 
@@ -402,14 +789,47 @@ impl Info {
     pub fn set_nonblocking(&mut self, nonblocking: bool) {
         self.nonblocking = nonblocking;
     }
+
+    pub fn recv_timeout(&self) -> Option<Duration> {
+        self.recv_timeout
+    }
+
+    pub fn set_recv_timeout(&mut self, timeout: Option<Duration>) {
+        self.recv_timeout = timeout;
+    }
+
+    pub fn send_timeout(&self) -> Option<Duration> {
+        self.send_timeout
+    }
+
+    pub fn set_send_timeout(&mut self, timeout: Option<Duration>) {
+        self.send_timeout = timeout;
+    }
+
+    pub fn recv_buf_size(&self) -> usize {
+        self.recv_buf_size
+    }
+
+    pub fn set_recv_buf_size(&mut self, size: usize) {
+        self.recv_buf_size = size;
+    }
+
+    pub fn send_buf_size(&self) -> usize {
+        self.send_buf_size
+    }
+
+    pub fn set_send_buf_size(&mut self, size: usize) {
+        self.send_buf_size = size;
+    }
 }
 
 /// The listener status of a stream unix socket.
 /// It contains a channel holding incoming connections.
 /// The nonblocking status of the reader end keeps the same with the socket.
-/// The writer end is always non-blocking. The connect function returns
-/// ECONNREFUSED rather than block when the channel is full.
+/// The writer end is always non-blocking. When the channel is full, a blocking
+/// connect fails with ECONNREFUSED rather than blocks.
 pub struct Listener {
+    socket_type: SocketType,
     channel: RwLock<Channel<Endpoint>>,
     notifier: Arc<RelayNotifier>,
 }
@@ -417,6 +837,7 @@ pub struct Listener {
 impl Listener {
     pub(super) fn new(
         capacity: usize,
+        socket_type: SocketType,
         nonblocking: bool,
         notifier: Arc<RelayNotifier>,
     ) -> Result<Self> {
@@ -425,11 +846,16 @@ impl Listener {
         channel.consumer().set_nonblocking(nonblocking);
 
         Ok(Self {
+            socket_type,
             channel: RwLock::new(channel),
             notifier,
         })
     }
 
+    pub fn socket_type(&self) -> SocketType {
+        self.socket_type
+    }
+
     pub fn capacity(&self) -> usize {
         let channel = self.channel.read().unwrap();
         channel.capacity()
@@ -9,7 +9,7 @@ use misc::resource_t;
 use process::Process;
 use signal::{sigset_t, SigSet};
 use std::convert::TryFrom;
-use time::{timespec_t, timeval_t};
+use time::{deadline_after, remaining_until, timespec_t, timeval_t};
 use util::mem_util::from_user;
 
 pub fn do_socket(domain: c_int, socket_type: c_int, protocol: c_int) -> Result<isize> {
@@ -114,6 +114,22 @@ pub fn do_accept(
     do_accept4(fd, addr, addr_len, 0)
 }
 
+/// Check whether a new fd can be allocated without exceeding the soft limit of
+/// `RLIMIT_NOFILE`.
+fn check_fd_available() -> Result<()> {
+    let current = current!();
+    let soft_rlimit_nofile = current
+        .rlimits()
+        .lock()
+        .unwrap()
+        .get(resource_t::RLIMIT_NOFILE)
+        .get_cur();
+    if current.files().lock().unwrap().min_free_fd() as u64 >= soft_rlimit_nofile {
+        return_errno!(EMFILE, "the number of open files reaches the limit");
+    }
+    Ok(())
+}
+
 pub fn do_accept4(
     fd: c_int,
     addr: *mut libc::sockaddr,
@@ -130,6 +146,8 @@ pub fn do_accept4(
     let close_on_spawn = file_flags.contains(FileFlags::SOCK_CLOEXEC);
 
     let file_ref = current!().file(fd as FileDesc)?;
+    // Check before accepting so that the connection is left in the queue
+    check_fd_available()?;
     if let Ok(socket) = file_ref.as_host_socket() {
         let (new_socket_file, sock_addr_option) = socket.accept(file_flags)?;
         let new_file_ref: Arc<dyn File> = Arc::new(new_socket_file);
@@ -164,8 +182,15 @@ pub fn do_accept4(
                     *addr_len = sock_addr.raw_len() as u32;
                 }
             } else {
+                // Like Linux, the address of an unbound peer is unnamed, which
+                // has only the address family
+                let family = (AddressFamily::LOCAL as libc::sa_family_t).to_ne_bytes();
+                let mut buf =
+                    unsafe { std::slice::from_raw_parts_mut(addr as *mut u8, *addr_len as usize) };
+                let copy_len = buf.len().min(family.len());
+                buf[..copy_len].copy_from_slice(&family[..copy_len]);
                 unsafe {
-                    *addr_len = 0;
+                    *addr_len = family.len() as u32;
                 }
             }
         }
@@ -202,18 +227,19 @@ pub fn do_setsockopt(
         "setsockopt: fd: {}, level: {}, optname: {}, optval: {:?}, optlen: {:?}",
         fd, level, optname, optval, optlen
     );
+    let optval = if optval.is_null() {
+        &[]
+    } else {
+        from_user::check_array(optval as *const u8, optlen as usize)?;
+        unsafe { std::slice::from_raw_parts(optval as *const u8, optlen as usize) }
+    };
+
     let file_ref = current!().file(fd as FileDesc)?;
     if let Ok(socket) = file_ref.as_host_socket() {
-        let ret = try_libc!(libc::ocall::setsockopt(
-            socket.raw_host_fd() as i32,
-            level,
-            optname,
-            optval,
-            optlen
-        ));
-        Ok(ret as isize)
+        socket.setsockopt(level, optname, optval)?;
+        Ok(0)
     } else if let Ok(unix_socket) = file_ref.as_unix_socket() {
-        warn!("setsockopt for unix socket is unimplemented");
+        unix_socket.setsockopt(level, optname, optval)?;
         Ok(0)
     } else {
         return_errno!(ENOTSOCK, "not a socket")
@@ -231,20 +257,27 @@ pub fn do_getsockopt(
         "getsockopt: fd: {}, level: {}, optname: {}, optval: {:?}, optlen: {:?}",
         fd, level, optname, optval, optlen
     );
+    from_user::check_mut_ptr(optlen)?;
+    let max_optlen = unsafe { *optlen } as usize;
+    let optval = if optval.is_null() {
+        &mut []
+    } else {
+        from_user::check_mut_array(optval as *mut u8, max_optlen)?;
+        unsafe { std::slice::from_raw_parts_mut(optval as *mut u8, max_optlen) }
+    };
+
     let file_ref = current!().file(fd as FileDesc)?;
-    let socket = file_ref.as_host_socket();
-
-    if let Ok(socket) = socket {
-        let ret = try_libc!(libc::ocall::getsockopt(
-            socket.raw_host_fd() as i32,
-            level,
-            optname,
-            optval,
-            optlen
-        ));
-        Ok(ret as isize)
+    if let Ok(socket) = file_ref.as_host_socket() {
+        let len = socket.getsockopt(level, optname, optval)?;
+        unsafe {
+            *optlen = len as u32;
+        }
+        Ok(0)
     } else if let Ok(unix_socket) = file_ref.as_unix_socket() {
-        warn!("getsockopt for unix socket is unimplemented");
+        let len = unix_socket.getsockopt(level, optname, optval)?;
+        unsafe {
+            *optlen = len as u32;
+        }
         Ok(0)
     } else {
         return_errno!(ENOTSOCK, "not a socket")
@@ -266,12 +299,15 @@ pub fn do_getpeername(
 
     let file_ref = current!().file(fd as FileDesc)?;
     if let Ok(socket) = file_ref.as_host_socket() {
-        let ret = try_libc!(libc::ocall::getpeername(
-            socket.raw_host_fd() as i32,
-            addr,
-            addr_len
-        ));
-        Ok(ret as isize)
+        let name = socket.peer_addr()?;
+        let mut dst = unsafe {
+            std::slice::from_raw_parts_mut(addr as *mut _ as *mut u8, *addr_len as usize)
+        };
+        name.copy_to_slice(dst);
+        unsafe {
+            *addr_len = name.len() as u32;
+        }
+        Ok(0)
     } else if let Ok(unix_socket) = file_ref.as_unix_socket() {
         let name = unix_socket.peer_addr()?;
         let mut dst = unsafe {
@@ -306,12 +342,15 @@ pub fn do_getsockname(
 
     let file_ref = current!().file(fd as FileDesc)?;
     if let Ok(socket) = file_ref.as_host_socket() {
-        let ret = try_libc!(libc::ocall::getsockname(
-            socket.raw_host_fd() as i32,
-            addr,
-            addr_len
-        ));
-        Ok(ret as isize)
+        let name = socket.addr()?;
+        let mut dst = unsafe {
+            std::slice::from_raw_parts_mut(addr as *mut _ as *mut u8, *addr_len as usize)
+        };
+        name.copy_to_slice(dst);
+        unsafe {
+            *addr_len = name.len() as u32;
+        }
+        Ok(0)
     } else if let Ok(unix_socket) = file_ref.as_unix_socket() {
         let name_opt = unix_socket.addr();
         if let Some(name) = name_opt {
@@ -534,17 +573,16 @@ pub fn do_recvmsg(fd: c_int, msg_mut_ptr: *mut msghdr_mut, flags_c: c_int) -> Re
     let flags = RecvFlags::from_bits_truncate(flags_c);
 
     let file_ref = current!().file(fd as FileDesc)?;
-    if let Ok(socket) = file_ref.as_host_socket() {
-        socket
-            .recvmsg(&mut msg_hdr_mut, flags)
-            .map(|bytes_recvd| bytes_recvd as isize)
+    let (bytes_recvd, flags_recvd) = if let Ok(socket) = file_ref.as_host_socket() {
+        socket.recvmsg(&mut msg_hdr_mut, flags)?
     } else if let Ok(socket) = file_ref.as_unix_socket() {
-        socket
-            .recvmsg(&mut msg_hdr_mut, flags)
-            .map(|bytes_recvd| bytes_recvd as isize)
+        socket.recvmsg(&mut msg_hdr_mut, flags)?
     } else {
         return_errno!(ENOTSOCK, "not a socket")
-    }
+    };
+
+    msg_hdr_mut.set_flags(flags_recvd);
+    Ok(bytes_recvd as isize)
 }
 
 pub fn do_sendmmsg(
@@ -558,42 +596,104 @@ pub fn do_sendmmsg(
         fd, msgvec_ptr, flags_c
     );
 
-    from_user::check_ptr(msgvec_ptr)?;
-
-    let mut msgvec = unsafe { std::slice::from_raw_parts_mut(msgvec_ptr, vlen as usize) };
+    let msgvec = {
+        from_user::check_mut_array(msgvec_ptr, vlen as usize)?;
+        unsafe { std::slice::from_raw_parts_mut(msgvec_ptr, vlen as usize) }
+    };
     let flags = SendFlags::from_bits_truncate(flags_c);
     let file_ref = current!().file(fd as FileDesc)?;
 
     if let Ok(socket) = file_ref.as_host_socket() {
         let mut send_count = 0;
-        for mmsg in (msgvec) {
-            if !mmsg.msg_hdr.check_member_ptrs().is_ok() {
-                break;
-            }
+        for mmsg in msgvec {
+            let result = mmsg
+                .msg_hdr
+                .check_member_ptrs()
+                .and_then(|_| unsafe { MsgHdr::from_c(&mmsg.msg_hdr) })
+                .and_then(|msg| socket.sendmsg(&msg, flags));
+            let bytes_sent = match result {
+                Ok(bytes_sent) => bytes_sent,
+                // Like Linux, the error is reported only if no message is sent,
+                // e.g., a partial success followed by EAGAIN returns the count
+                Err(e) if send_count == 0 => return Err(e),
+                Err(_) => break,
+            };
+            mmsg.msg_len = bytes_sent as u32;
+            send_count += 1;
+        }
 
-            let msg = unsafe {
-                if let Ok(msg) = MsgHdr::from_c({ &mmsg.msg_hdr }) {
-                    msg
-                } else {
-                    break;
-                }
+        Ok(send_count as isize)
+    } else if let Ok(socket) = file_ref.as_unix_socket() {
+        return_errno!(EOPNOTSUPP, "does not support unix socket")
+    } else {
+        return_errno!(ENOTSOCK, "not a socket")
+    }
+}
+
+pub fn do_recvmmsg(
+    fd: c_int,
+    msgvec_ptr: *mut mmsghdr_mut,
+    vlen: c_uint,
+    flags_c: c_int,
+    timeout_ptr: *const timespec_t,
+) -> Result<isize> {
+    debug!(
+        "recvmmsg: fd: {}, msg: {:?}, vlen: {}, flags: 0x{:x}",
+        fd, msgvec_ptr, vlen, flags_c
+    );
+
+    let deadline = if timeout_ptr.is_null() {
+        None
+    } else {
+        from_user::check_ptr(timeout_ptr)?;
+        let timeout = timespec_t::from_raw_ptr(timeout_ptr)?.as_duration();
+        Some(deadline_after(&timeout)?)
+    };
+
+    let msgvec = {
+        from_user::check_mut_array(msgvec_ptr, vlen as usize)?;
+        unsafe { std::slice::from_raw_parts_mut(msgvec_ptr, vlen as usize) }
+    };
+    let mut flags = RecvFlags::from_bits_truncate(flags_c);
+    let file_ref = current!().file(fd as FileDesc)?;
+
+    if let Ok(socket) = file_ref.as_host_socket() {
+        let mut recv_count = 0;
+        for mmsg in msgvec {
+            let result = mmsg
+                .msg_hdr
+                .check_member_ptrs()
+                .and_then(|_| unsafe { MsgHdrMut::from_c(&mut mmsg.msg_hdr) })
+                .and_then(|mut msg_hdr_mut| {
+                    let (bytes_recvd, flags_recvd) = socket.recvmsg(&mut msg_hdr_mut, flags)?;
+                    msg_hdr_mut.set_flags(flags_recvd);
+                    Ok(bytes_recvd)
+                });
+            let bytes_recvd = match result {
+                Ok(bytes_recvd) => bytes_recvd,
+                // Like Linux, the error is reported only if no message is received,
+                // so that the messages taken from the socket are not lost
+                Err(e) if recv_count == 0 => return Err(e),
+                Err(_) => break,
             };
+            mmsg.msg_len = bytes_recvd as u32;
+            recv_count += 1;
+
+            // Like Linux, the timeout is checked only after a message is received
+            if let Some(deadline) = deadline.as_ref() {
+                match remaining_until(deadline) {
+                    Ok(remain) if !remain.is_zero() => (),
+                    _ => break,
+                }
+            }
 
-            if socket
-                .sendmsg(&msg, flags)
-                .map(|bytes_sent| {
-                    mmsg.msg_len = bytes_sent as u32;
-                    mmsg.msg_len
-                })
-                .is_ok()
-            {
-                send_count += 1;
-            } else {
-                break;
+            // Do not block for the rest of the messages
+            if flags.contains(RecvFlags::MSG_WAITFORONE) {
+                flags |= RecvFlags::MSG_DONTWAIT;
             }
         }
 
-        Ok(send_count as isize)
+        Ok(recv_count as isize)
     } else if let Ok(socket) = file_ref.as_unix_socket() {
         return_errno!(EOPNOTSUPP, "does not support unix socket")
     } else {
@@ -192,6 +192,19 @@ pub fn do_clock_gettime(clockid: ClockID) -> Result<timespec_t> {
     Ok(tv)
 }
 
+/// Returns the deadline of the `CLOCK_MONOTONIC` clock after the timeout.
+pub fn deadline_after(timeout: &Duration) -> Result<Duration> {
+    let now = do_clock_gettime(ClockID::CLOCK_MONOTONIC)?.as_duration();
+    Ok(now + *timeout)
+}
+
+/// Returns the time left until the deadline of the `CLOCK_MONOTONIC` clock,
+/// which is zero if the deadline has passed.
+pub fn remaining_until(deadline: &Duration) -> Result<Duration> {
+    let now = do_clock_gettime(ClockID::CLOCK_MONOTONIC)?.as_duration();
+    Ok(deadline.checked_sub(now).unwrap_or_default())
+}
+
 pub fn do_clock_getres(clockid: ClockID) -> Result<timespec_t> {
     extern "C" {
         fn occlum_ocall_clock_getres(clockid: clockid_t, res: *mut timespec_t) -> sgx_status_t;
@@ -23,6 +23,7 @@
 
 #define MAXEVENTS 64
 #define TEST_DATA 678
+#define NUM_SOCKET_PAIRS 8
 
 struct thread_arg {
     pthread_t tid;
@@ -163,6 +164,211 @@ int test_epoll_ctl_eventfd() {
     return 0;
 }
 
+// This test intends to test that a level-triggered registration is reported
+// as long as the file is ready, while an edge-triggered one is reported only
+// once for each new event
+int test_epoll_level_and_edge_triggered() {
+    int sockets[2];
+    if (socketpair(AF_UNIX, SOCK_STREAM, 0, sockets) < 0) {
+        THROW_ERROR("opening stream socket pair");
+    }
+
+    int lt_epfd = epoll_create1(0);
+    int et_epfd = epoll_create1(0);
+    if (lt_epfd == -1 || et_epfd == -1) {
+        close_files(4, sockets[0], sockets[1], lt_epfd, et_epfd);
+        THROW_ERROR("epoll_create failed");
+    }
+
+    struct epoll_event event;
+    event.data.fd = sockets[0];
+    event.events = EPOLLIN;
+    int ret = epoll_ctl(lt_epfd, EPOLL_CTL_ADD, sockets[0], &event);
+    event.events = EPOLLIN | EPOLLET;
+    ret |= epoll_ctl(et_epfd, EPOLL_CTL_ADD, sockets[0], &event);
+    if (ret == -1) {
+        close_files(4, sockets[0], sockets[1], lt_epfd, et_epfd);
+        THROW_ERROR("epoll_ctl add failed");
+    }
+
+    // The socket stays readable since the data is never read
+    uint64_t data = TEST_DATA;
+    if (write(sockets[1], &data, sizeof(data)) != sizeof(data)) {
+        close_files(4, sockets[0], sockets[1], lt_epfd, et_epfd);
+        THROW_ERROR("failed to write the socket");
+    }
+
+    struct epoll_event events[MAXEVENTS] = {0};
+    for (int i = 0; i < 2; i++) {
+        if (epoll_wait(lt_epfd, events, MAXEVENTS, 0) != 1) {
+            close_files(4, sockets[0], sockets[1], lt_epfd, et_epfd);
+            THROW_ERROR("level-triggered event should be reported while readable");
+        }
+    }
+
+    if (epoll_wait(et_epfd, events, MAXEVENTS, 0) != 1 ||
+            epoll_wait(et_epfd, events, MAXEVENTS, 0) != 0) {
+        close_files(4, sockets[0], sockets[1], lt_epfd, et_epfd);
+        THROW_ERROR("edge-triggered event should be reported only once");
+    }
+
+    // New data is a new event
+    if (write(sockets[1], &data, sizeof(data)) != sizeof(data)) {
+        close_files(4, sockets[0], sockets[1], lt_epfd, et_epfd);
+        THROW_ERROR("failed to write the socket");
+    }
+    if (epoll_wait(et_epfd, events, MAXEVENTS, 0) != 1) {
+        close_files(4, sockets[0], sockets[1], lt_epfd, et_epfd);
+        THROW_ERROR("edge-triggered event should be reported for new data");
+    }
+
+    close_files(4, sockets[0], sockets[1], lt_epfd, et_epfd);
+    return 0;
+}
+
+// This test intends to test that a one-shot registration is disabled after
+// being reported until it is rearmed by EPOLL_CTL_MOD
+int test_epoll_oneshot() {
+    int sockets[2];
+    if (socketpair(AF_UNIX, SOCK_STREAM, 0, sockets) < 0) {
+        THROW_ERROR("opening stream socket pair");
+    }
+
+    int epfd = epoll_create1(0);
+    if (epfd == -1) {
+        close_files(2, sockets[0], sockets[1]);
+        THROW_ERROR("epoll_create failed");
+    }
+
+    struct epoll_event event;
+    event.data.fd = sockets[0];
+    event.events = EPOLLIN | EPOLLONESHOT;
+    if (epoll_ctl(epfd, EPOLL_CTL_ADD, sockets[0], &event) == -1) {
+        close_files(3, sockets[0], sockets[1], epfd);
+        THROW_ERROR("epoll_ctl add failed");
+    }
+
+    uint64_t data = TEST_DATA;
+    if (write(sockets[1], &data, sizeof(data)) != sizeof(data)) {
+        close_files(3, sockets[0], sockets[1], epfd);
+        THROW_ERROR("failed to write the socket");
+    }
+
+    struct epoll_event events[MAXEVENTS] = {0};
+    if (epoll_wait(epfd, events, MAXEVENTS, 0) != 1) {
+        close_files(3, sockets[0], sockets[1], epfd);
+        THROW_ERROR("one-shot event should be reported");
+    }
+
+    // Neither the readiness nor new data is reported before rearming
+    if (write(sockets[1], &data, sizeof(data)) != sizeof(data)) {
+        close_files(3, sockets[0], sockets[1], epfd);
+        THROW_ERROR("failed to write the socket");
+    }
+    if (epoll_wait(epfd, events, MAXEVENTS, 0) != 0) {
+        close_files(3, sockets[0], sockets[1], epfd);
+        THROW_ERROR("one-shot event should not be reported before rearming");
+    }
+
+    if (epoll_ctl(epfd, EPOLL_CTL_MOD, sockets[0], &event) == -1) {
+        close_files(3, sockets[0], sockets[1], epfd);
+        THROW_ERROR("epoll_ctl mod failed");
+    }
+    if (epoll_wait(epfd, events, MAXEVENTS, 0) != 1) {
+        close_files(3, sockets[0], sockets[1], epfd);
+        THROW_ERROR("one-shot event should be reported after rearming");
+    }
+
+    close_files(3, sockets[0], sockets[1], epfd);
+    return 0;
+}
+
+// This test intends to test that closing one of the epoll instances that
+// monitor a file does not affect the notification of the others
+int test_epoll_close_another_epoll() {
+    int sockets[2];
+    if (socketpair(AF_UNIX, SOCK_STREAM, 0, sockets) < 0) {
+        THROW_ERROR("opening stream socket pair");
+    }
+
+    int epfd_closed = epoll_create1(0);
+    int epfd = epoll_create1(0);
+    if (epfd_closed == -1 || epfd == -1) {
+        close_files(4, sockets[0], sockets[1], epfd_closed, epfd);
+        THROW_ERROR("epoll_create failed");
+    }
+
+    struct epoll_event event;
+    event.data.fd = sockets[0];
+    event.events = EPOLLIN;
+    if (epoll_ctl(epfd_closed, EPOLL_CTL_ADD, sockets[0], &event) == -1 ||
+            epoll_ctl(epfd, EPOLL_CTL_ADD, sockets[0], &event) == -1) {
+        close_files(4, sockets[0], sockets[1], epfd_closed, epfd);
+        THROW_ERROR("epoll_ctl add failed");
+    }
+    close(epfd_closed);
+
+    uint64_t data = TEST_DATA;
+    if (write(sockets[1], &data, sizeof(data)) != sizeof(data)) {
+        close_files(3, sockets[0], sockets[1], epfd);
+        THROW_ERROR("failed to write the socket");
+    }
+
+    struct epoll_event events[MAXEVENTS] = {0};
+    if (epoll_wait(epfd, events, MAXEVENTS, 0) != 1) {
+        close_files(3, sockets[0], sockets[1], epfd);
+        THROW_ERROR("the remaining epoll instance should be notified");
+    }
+
+    close_files(3, sockets[0], sockets[1], epfd);
+    return 0;
+}
+
+int test_epoll_ready_set() {
+    int sockets[NUM_SOCKET_PAIRS][2];
+    int epfd = epoll_create1(0);
+    if (epfd == -1) {
+        THROW_ERROR("epoll_create failed");
+    }
+    for (int i = 0; i < NUM_SOCKET_PAIRS; i++) {
+        if (socketpair(AF_UNIX, SOCK_STREAM, 0, sockets[i]) < 0) {
+            THROW_ERROR("opening stream socket pair");
+        }
+        struct epoll_event event = { .events = EPOLLIN, .data.fd = sockets[i][0] };
+        if (epoll_ctl(epfd, EPOLL_CTL_ADD, sockets[i][0], &event) == -1) {
+            THROW_ERROR("epoll_ctl add failed");
+        }
+    }
+
+    // Only the sockets that become readable are reported
+    uint64_t data = TEST_DATA;
+    for (int i = 0; i < NUM_SOCKET_PAIRS; i += 2) {
+        if (write(sockets[i][1], &data, sizeof(data)) != sizeof(data)) {
+            THROW_ERROR("failed to write the socket");
+        }
+    }
+    struct epoll_event events[MAXEVENTS] = {0};
+    int nfds = epoll_wait(epfd, events, MAXEVENTS, 0);
+    if (nfds != NUM_SOCKET_PAIRS / 2) {
+        THROW_ERROR("half of the sockets should be ready");
+    }
+    for (int i = 0; i < nfds; i++) {
+        int is_written = 0;
+        for (int j = 0; j < NUM_SOCKET_PAIRS; j += 2) {
+            is_written |= events[i].data.fd == sockets[j][0];
+        }
+        if (!is_written || events[i].events != EPOLLIN) {
+            THROW_ERROR("a socket that is not written is reported");
+        }
+    }
+
+    for (int i = 0; i < NUM_SOCKET_PAIRS; i++) {
+        close_files(2, sockets[i][0], sockets[i][1]);
+    }
+    close(epfd);
+    return 0;
+}
+
 // ============================================================================
 // Test suite main
 // ============================================================================
@@ -170,6 +376,10 @@ int test_epoll_ctl_eventfd() {
 static test_case_t test_cases[] = {
     TEST_CASE(test_epoll_ctl_eventfd),
     TEST_CASE(test_epoll_ctl_uds),
+    TEST_CASE(test_epoll_level_and_edge_triggered),
+    TEST_CASE(test_epoll_oneshot),
+    TEST_CASE(test_epoll_close_another_epoll),
+    TEST_CASE(test_epoll_ready_set),
 };
 
 int main() {
@@ -3,6 +3,7 @@
 #include <sys/select.h>
 #include <sys/socket.h>
 #include <sys/syscall.h>
+#include <sys/uio.h>
 #include <sys/wait.h>
 #include <fcntl.h>
 #include <unistd.h>
@@ -144,6 +145,54 @@ int test_read_write() {
     return 0;
 }
 
+int test_readv_writev() {
+    int event_fd = eventfd(0, 0);
+    if (event_fd < 0) {
+        THROW_ERROR("failed to create an eventfd");
+    }
+
+    // The counter is written and read across three buffers as a whole
+    uint64_t data_sent = TEST_DATA;
+    struct iovec send_iov[3] = {
+        { .iov_base = (char *) &data_sent, .iov_len = 2 },
+        { .iov_base = (char *) &data_sent + 2, .iov_len = 2 },
+        { .iov_base = (char *) &data_sent + 4, .iov_len = 4 },
+    };
+    if (writev(event_fd, send_iov, 3) != sizeof(uint64_t)) {
+        close(event_fd);
+        THROW_ERROR("failed to writev");
+    }
+
+    uint64_t data_recv = 0;
+    struct iovec recv_iov[3] = {
+        { .iov_base = (char *) &data_recv, .iov_len = 4 },
+        { .iov_base = (char *) &data_recv + 4, .iov_len = 2 },
+        { .iov_base = (char *) &data_recv + 6, .iov_len = 2 },
+    };
+    if (readv(event_fd, recv_iov, 3) != sizeof(uint64_t)) {
+        close(event_fd);
+        THROW_ERROR("failed to readv");
+    }
+    if (data_recv != TEST_DATA) {
+        close(event_fd);
+        THROW_ERROR("data received is not as expected");
+    }
+
+    // A buffer larger than the cap of a single read gets only the counter,
+    // instead of blocking to read more
+    static char large_buf[256 * 1024];
+    struct iovec large_iov = { .iov_base = large_buf, .iov_len = sizeof(large_buf) };
+    if (write(event_fd, &data_sent, sizeof(data_sent)) != sizeof(data_sent) ||
+            readv(event_fd, &large_iov, 1) != sizeof(uint64_t) ||
+            memcmp(large_buf, &data_sent, sizeof(data_sent)) != 0) {
+        close(event_fd);
+        THROW_ERROR("failed to readv into large buffers");
+    }
+
+    close(event_fd);
+    return 0;
+}
+
 int test_select_with_socket() {
     fd_set rfds, wfds;
     int ret = 0;
@@ -251,6 +300,7 @@ static test_case_t test_cases[] = {
     TEST_CASE(test_fcntl_set_flags),
     TEST_CASE(test_create_with_flags),
     TEST_CASE(test_read_write),
+    TEST_CASE(test_readv_writev),
     TEST_CASE(test_epoll_with_socket),
     TEST_CASE(test_poll_with_socket),
     TEST_CASE(test_select_with_socket),
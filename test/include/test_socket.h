@@ -0,0 +1,95 @@
+#ifndef __TEST_SOCKET_H
+#define __TEST_SOCKET_H
+
+#include <poll.h>
+#include <string.h>
+#include <sys/socket.h>
+#include "test.h"
+
+// ============================================================================
+// Checks shared by the connected stream sockets of all domains
+// ============================================================================
+
+// Creates a pair of connected stream sockets in fds[0] and fds[1]
+typedef int(*stream_pair_func_t)(int fds[2]);
+
+// A check may close the sockets it is given, and then sets them to -1
+typedef int(*stream_pair_check_func_t)(int fds[2]);
+
+typedef struct {
+    const char                 *name;
+    stream_pair_check_func_t    func;
+} stream_pair_check_t;
+
+#define STREAM_PAIR_CHECK(name)     { STR(name), name }
+
+// The socket shut down for writing can still read what the peer sends
+int check_read_after_shutdown_write(int fds[2]) {
+    char buf[16];
+    if (shutdown(fds[0], SHUT_WR) < 0 || recv(fds[1], buf, sizeof(buf), 0) != 0) {
+        THROW_ERROR("the peer should see the end of file");
+    }
+    if (send(fds[1], "abc", 3, 0) != 3) {
+        THROW_ERROR("failed to send to a half-closed socket");
+    }
+
+    struct pollfd pfd = { .fd = fds[0], .events = POLLIN };
+    if (poll(&pfd, 1, 1000) != 1 || !(pfd.revents & POLLIN) || (pfd.revents & POLLHUP)) {
+        THROW_ERROR("the half-closed socket should be readable but not hung up");
+    }
+    if (recv(fds[0], buf, sizeof(buf), 0) != 3 || strncmp(buf, "abc", 3) != 0) {
+        THROW_ERROR("failed to read from a half-closed socket");
+    }
+
+    // The end of file is read after the peer is closed
+    close(fds[1]);
+    fds[1] = -1;
+    pfd.events = POLLIN | POLLRDHUP;
+    if (poll(&pfd, 1, 1000) != 1 || recv(fds[0], buf, sizeof(buf), 0) != 0) {
+        THROW_ERROR("the end of file should be read after the peer closes");
+    }
+    return 0;
+}
+
+// The write shutdown of the peer is RDHUP only, and the socket hangs up once
+// it is also shut down for writing
+int check_poll_rdhup_and_hup(int fds[2]) {
+    if (shutdown(fds[1], SHUT_WR) < 0) {
+        THROW_ERROR("failed to shutdown");
+    }
+    struct pollfd pfd = { .fd = fds[0], .events = POLLIN | POLLOUT | POLLRDHUP };
+    if (poll(&pfd, 1, 1000) != 1 || pfd.revents != (POLLIN | POLLOUT | POLLRDHUP)) {
+        THROW_ERROR("only RDHUP is expected after the peer shuts down writing");
+    }
+
+    if (shutdown(fds[0], SHUT_WR) < 0) {
+        THROW_ERROR("failed to shutdown");
+    }
+    if (poll(&pfd, 1, 1000) != 1 || !(pfd.revents & POLLHUP) || !(pfd.revents & POLLRDHUP)) {
+        THROW_ERROR("both HUP and RDHUP are expected after both ends shut down writing");
+    }
+    return 0;
+}
+
+// Runs every check on a new pair of connected sockets
+int run_stream_pair_checks(stream_pair_func_t make_pair) {
+    static const stream_pair_check_t checks[] = {
+        STREAM_PAIR_CHECK(check_read_after_shutdown_write),
+        STREAM_PAIR_CHECK(check_poll_rdhup_and_hup),
+    };
+
+    for (int ci = 0; ci < ARRAY_SIZE(checks); ci++) {
+        int fds[2];
+        if (make_pair(fds) < 0) {
+            THROW_ERROR("failed to create a pair of connected sockets");
+        }
+        int ret = checks[ci].func(fds);
+        close_files(2, fds[0], fds[1]);
+        if (ret < 0) {
+            THROW_ERROR("the check %s failed", checks[ci].name);
+        }
+    }
+    return 0;
+}
+
+#endif /* __TEST_SOCKET_H */
@@ -1,5 +1,7 @@
 #define _GNU_SOURCE
 #include <net/if.h>
+#include <netinet/in.h>
+#include <arpa/inet.h>
 #include <sys/socket.h>
 #include <sys/types.h>
 #include <sys/ioctl.h>
@@ -573,6 +575,28 @@ int test_ioctl_SIOCGIFCONF(void) {
     return 0;
 }
 
+int test_ioctl_SIOCGIFADDR(void) {
+    int sock = socket(AF_INET, SOCK_DGRAM, 0);
+    if (sock < 0) {
+        THROW_ERROR("failed to create a socket");
+    }
+
+    struct ifreq req;
+    memset(&req, 0, sizeof(req));
+    strncpy(req.ifr_name, "lo", IFNAMSIZ - 1);
+    if (ioctl(sock, SIOCGIFADDR, &req) < 0) {
+        close(sock);
+        THROW_ERROR("failed to get the address of the loopback interface");
+    }
+    close(sock);
+
+    struct sockaddr_in *addr = (struct sockaddr_in *)&req.ifr_addr;
+    if (addr->sin_family != AF_INET || addr->sin_addr.s_addr != htonl(INADDR_LOOPBACK)) {
+        THROW_ERROR("wrong address of the loopback interface");
+    }
+    return 0;
+}
+
 int test_ioctl_FIONBIO(void) {
     int test_sock[2], sock;
     test_sock[0] = socket(AF_INET, SOCK_STREAM, 0);
@@ -668,6 +692,7 @@ static test_case_t test_cases[] = {
     TEST_CASE(test_sgx_ioctl_SGXIOC_GENERATE_AND_VERIFY_DCAP_QUOTE),
 #endif
     TEST_CASE(test_ioctl_SIOCGIFCONF),
+    TEST_CASE(test_ioctl_SIOCGIFADDR),
     TEST_CASE(test_ioctl_FIONBIO),
     TEST_CASE(test_ioctl_FIOCLEX),
 };
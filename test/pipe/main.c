@@ -9,6 +9,7 @@
 #include <sys/ioctl.h>
 #include <fcntl.h>
 #include <poll.h>
+#include <pthread.h>
 #include <unistd.h>
 #include <stdlib.h>
 #include <stdio.h>
@@ -385,6 +386,121 @@ int test_ioctl_fionread() {
     return 0;
 }
 
+#define PING_PONG_NTHREADS  8
+#define PING_PONG_NROUNDS   2000
+
+struct ping_pong_args {
+    int rd_fd;
+    int wr_fd;
+};
+
+static void *ping_pong_echo(void *_args) {
+    struct ping_pong_args *args = (struct ping_pong_args *)_args;
+    char byte;
+    for (int i = 0; i < PING_PONG_NROUNDS; i++) {
+        if (read(args->rd_fd, &byte, 1) != 1 ||
+                write(args->wr_fd, &byte, 1) != 1) {
+            return (void *) -1;
+        }
+    }
+    return NULL;
+}
+
+// Each round trip blocks on an empty pipe and relies on the writer to wake up
+// the reader. A missed wakeup makes this test hang.
+int test_wait_wake_stress() {
+    int ping_fds[PING_PONG_NTHREADS][2];
+    int pong_fds[PING_PONG_NTHREADS][2];
+    struct ping_pong_args args[PING_PONG_NTHREADS];
+    pthread_t threads[PING_PONG_NTHREADS];
+
+    for (int ti = 0; ti < PING_PONG_NTHREADS; ti++) {
+        if (pipe(ping_fds[ti]) < 0 || pipe(pong_fds[ti]) < 0) {
+            THROW_ERROR("failed to create pipes");
+        }
+        args[ti].rd_fd = ping_fds[ti][0];
+        args[ti].wr_fd = pong_fds[ti][1];
+        if (pthread_create(&threads[ti], NULL, ping_pong_echo, &args[ti]) != 0) {
+            THROW_ERROR("failed to create a thread");
+        }
+    }
+
+    for (int i = 0; i < PING_PONG_NROUNDS; i++) {
+        for (int ti = 0; ti < PING_PONG_NTHREADS; ti++) {
+            char byte = (char)i;
+            if (write(ping_fds[ti][1], &byte, 1) != 1) {
+                THROW_ERROR("failed to write the ping");
+            }
+        }
+        for (int ti = 0; ti < PING_PONG_NTHREADS; ti++) {
+            char byte;
+            if (read(pong_fds[ti][0], &byte, 1) != 1 || byte != (char)i) {
+                THROW_ERROR("failed to read the pong");
+            }
+        }
+    }
+
+    for (int ti = 0; ti < PING_PONG_NTHREADS; ti++) {
+        void *ret;
+        if (pthread_join(threads[ti], &ret) != 0 || ret != NULL) {
+            THROW_ERROR("the echo thread failed");
+        }
+        free_pipe(ping_fds[ti]);
+        free_pipe(pong_fds[ti]);
+    }
+    return 0;
+}
+
+#define WAKE_RESET_NWRITERS     8
+#define WAKE_RESET_NBYTES       4000
+
+static void *write_bytes_one_by_one(void *_wr_fd) {
+    int wr_fd = *(int *)_wr_fd;
+    char byte = 'x';
+    for (int i = 0; i < WAKE_RESET_NBYTES; i++) {
+        if (write(wr_fd, &byte, 1) != 1) {
+            return (void *) -1;
+        }
+    }
+    return NULL;
+}
+
+// The reader resets its waiter before every blocking read, while the writers
+// keep waking it up concurrently. If any wakeup is lost, the reader would fall
+// asleep with data available and this test would hang.
+int test_wake_reset_interleave() {
+    int pipe_fds[2];
+    pthread_t writers[WAKE_RESET_NWRITERS];
+
+    if (pipe(pipe_fds) < 0) {
+        THROW_ERROR("failed to create a pipe");
+    }
+    for (int ti = 0; ti < WAKE_RESET_NWRITERS; ti++) {
+        if (pthread_create(&writers[ti], NULL, write_bytes_one_by_one, &pipe_fds[1]) != 0) {
+            THROW_ERROR("failed to create a thread");
+        }
+    }
+
+    size_t total_len = 0;
+    char buf[64];
+    while (total_len < WAKE_RESET_NWRITERS * WAKE_RESET_NBYTES) {
+        ssize_t len = read(pipe_fds[0], buf, sizeof(buf));
+        if (len <= 0) {
+            THROW_ERROR("failed to read the pipe");
+        }
+        total_len += len;
+    }
+
+    for (int ti = 0; ti < WAKE_RESET_NWRITERS; ti++) {
+        void *ret;
+        if (pthread_join(writers[ti], &ret) != 0 || ret != NULL) {
+            THROW_ERROR("the writer thread failed");
+        }
+    }
+    free_pipe(pipe_fds);
+    return 0;
+}
+
 // ============================================================================
 // Test suite
 // ============================================================================
@@ -401,6 +517,8 @@ static test_case_t test_cases[] = {
     TEST_CASE(test_epoll_no_timeout),
     TEST_CASE(test_select_read_write),
     TEST_CASE(test_ioctl_fionread),
+    TEST_CASE(test_wait_wake_stress),
+    TEST_CASE(test_wake_reset_interleave),
 };
 
 int main(int argc, const char *argv[]) {
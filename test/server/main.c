@@ -6,15 +6,23 @@
 #include <stdlib.h>
 #include <stdio.h>
 #include <string.h>
+#include <time.h>
 #include <unistd.h>
 #include <arpa/inet.h>
+#include <net/if.h>
 #include <netinet/in.h>
+#include <netinet/tcp.h>
 #include <sys/types.h>
+#include <sys/ioctl.h>
+#include <sys/sendfile.h>
+#include <linux/sockios.h>
 #include <sys/socket.h>
+#include <sys/select.h>
 #include <sys/wait.h>
 #include <pthread.h>
 
 #include "test.h"
+#include "test_socket.h"
 
 #define ECHO_MSG "msg for client/server test"
 #define RESPONSE "ACK"
@@ -67,6 +75,64 @@ int connect_with_child(int port, int *child_pid) {
     return connected_fd;
 }
 
+// Connect a socket to a listener on a loopback port chosen by the system.
+// fds[0] is the connecting socket and fds[1] is the accepted one.
+int tcp_socket_pair(int fds[2]) {
+    struct sockaddr_in servaddr;
+    socklen_t addr_len = sizeof(servaddr);
+    memset(&servaddr, 0, sizeof(servaddr));
+    servaddr.sin_family = AF_INET;
+    servaddr.sin_addr.s_addr = htonl(INADDR_LOOPBACK);
+
+    int listen_fd = socket(AF_INET, SOCK_STREAM, 0);
+    if (listen_fd < 0) {
+        THROW_ERROR("create socket error");
+    }
+    if (bind(listen_fd, (struct sockaddr *) &servaddr, sizeof(servaddr)) < 0 ||
+            getsockname(listen_fd, (struct sockaddr *) &servaddr, &addr_len) < 0 ||
+            listen(listen_fd, 1) < 0) {
+        close(listen_fd);
+        THROW_ERROR("failed to listen");
+    }
+
+    fds[0] = socket(AF_INET, SOCK_STREAM, 0);
+    if (fds[0] < 0 || connect(fds[0], (struct sockaddr *) &servaddr, sizeof(servaddr)) < 0) {
+        close_files(2, listen_fd, fds[0]);
+        THROW_ERROR("failed to connect");
+    }
+    fds[1] = accept(listen_fd, NULL, NULL);
+    close(listen_fd);
+    if (fds[1] < 0) {
+        close(fds[0]);
+        THROW_ERROR("failed to accept");
+    }
+    return 0;
+}
+
+// Create a receiving UDP socket bound to the IP address and a port chosen by
+// the system, and an unbound sending socket. `addr` is set to the loopback
+// address with the bound port, to which the sending socket can send.
+int udp_socket_pair(in_addr_t ip, int fds[2], struct sockaddr_in *addr) {
+    socklen_t addr_len = sizeof(*addr);
+    memset(addr, 0, sizeof(*addr));
+    addr->sin_family = AF_INET;
+    addr->sin_addr.s_addr = ip;
+
+    fds[0] = socket(AF_INET, SOCK_DGRAM, 0);
+    fds[1] = socket(AF_INET, SOCK_DGRAM, 0);
+    if (fds[0] < 0 || fds[1] < 0) {
+        close_files(2, fds[0], fds[1]);
+        THROW_ERROR("create socket error");
+    }
+    if (bind(fds[0], (struct sockaddr *) addr, sizeof(*addr)) < 0 ||
+            getsockname(fds[0], (struct sockaddr *) addr, &addr_len) < 0) {
+        close_files(2, fds[0], fds[1]);
+        THROW_ERROR("bind socket failed");
+    }
+    addr->sin_addr.s_addr = htonl(INADDR_LOOPBACK);
+    return 0;
+}
+
 int neogotiate_msg(int client_fd) {
     char buf[16];
     if (write(client_fd, ECHO_MSG, strlen(ECHO_MSG)) < 0) {
@@ -375,6 +441,1893 @@ int test_poll_events_unchanged() {
     return 0;
 }
 
+int test_sockopt_reuseaddr() {
+    int sock = socket(AF_INET, SOCK_STREAM, 0);
+    if (sock < 0) {
+        THROW_ERROR("create socket error");
+    }
+
+    int reuse = 1;
+    if (setsockopt(sock, SOL_SOCKET, SO_REUSEADDR, &reuse, sizeof(reuse)) < 0) {
+        close(sock);
+        THROW_ERROR("setsockopt SO_REUSEADDR failed");
+    }
+
+    int actual = 0;
+    socklen_t optlen = sizeof(actual);
+    if (getsockopt(sock, SOL_SOCKET, SO_REUSEADDR, &actual, &optlen) < 0) {
+        close(sock);
+        THROW_ERROR("getsockopt SO_REUSEADDR failed");
+    }
+
+    if (actual != 1 || optlen != sizeof(actual)) {
+        close(sock);
+        THROW_ERROR("SO_REUSEADDR value mismatch");
+    }
+
+    close(sock);
+    return 0;
+}
+
+int test_nonblocking_connect_refused() {
+    int sock = socket(AF_INET, SOCK_STREAM | SOCK_NONBLOCK, 0);
+    if (sock < 0) {
+        THROW_ERROR("create socket error");
+    }
+
+    // No one is listening on this port
+    struct sockaddr_in servaddr;
+    memset(&servaddr, 0, sizeof(servaddr));
+    servaddr.sin_family = AF_INET;
+    servaddr.sin_addr.s_addr = htonl(INADDR_LOOPBACK);
+    servaddr.sin_port = htons(8806);
+    int ret = connect(sock, (struct sockaddr *) &servaddr, sizeof(servaddr));
+    if (ret == 0 || (errno != EINPROGRESS && errno != ECONNREFUSED)) {
+        close(sock);
+        THROW_ERROR("nonblocking connect should fail with EINPROGRESS");
+    }
+
+    if (ret < 0 && errno == EINPROGRESS) {
+        struct pollfd polls[] = {
+            { .fd = sock, .events = POLLOUT }
+        };
+        if (poll(polls, 1, 1000) != 1) {
+            close(sock);
+            THROW_ERROR("poll should report the completion of connect");
+        }
+
+        int error = 0;
+        socklen_t optlen = sizeof(error);
+        if (getsockopt(sock, SOL_SOCKET, SO_ERROR, &error, &optlen) < 0) {
+            close(sock);
+            THROW_ERROR("getsockopt SO_ERROR failed");
+        }
+        if (error != ECONNREFUSED) {
+            close(sock);
+            THROW_ERROR("connect should be refused");
+        }
+    }
+
+    close(sock);
+    return 0;
+}
+
+static volatile int sigpipe_count = 0;
+
+static void handle_sigpipe(int signum) {
+    sigpipe_count++;
+}
+
+int test_udp_shutdown_write() {
+    int sock = socket(AF_INET, SOCK_DGRAM, 0);
+    if (sock < 0) {
+        THROW_ERROR("create socket error");
+    }
+
+    struct sockaddr_in servaddr;
+    memset(&servaddr, 0, sizeof(servaddr));
+    servaddr.sin_family = AF_INET;
+    servaddr.sin_addr.s_addr = htonl(INADDR_LOOPBACK);
+    servaddr.sin_port = htons(8807);
+    if (connect(sock, (struct sockaddr *) &servaddr, sizeof(servaddr)) < 0) {
+        close(sock);
+        THROW_ERROR("connect error");
+    }
+
+    if (shutdown(sock, SHUT_WR) < 0) {
+        close(sock);
+        THROW_ERROR("shutdown error");
+    }
+
+    // Like Linux, no SIGPIPE is raised for a datagram socket
+    sigpipe_count = 0;
+    signal(SIGPIPE, handle_sigpipe);
+    int ret = send(sock, DEFAULT_MSG, sizeof(DEFAULT_MSG), 0);
+    int send_errno = errno;
+    signal(SIGPIPE, SIG_DFL);
+    if (ret >= 0 || send_errno != EPIPE) {
+        close(sock);
+        THROW_ERROR("send after shutdown should fail with EPIPE");
+    }
+    if (sigpipe_count != 0) {
+        close(sock);
+        THROW_ERROR("send on a datagram socket should not raise SIGPIPE");
+    }
+
+    close(sock);
+    return 0;
+}
+
+int test_recvmmsg() {
+    int ret = 0;
+    char bufs[4][32];
+    struct iovec iovs[4];
+    struct mmsghdr msgs[4];
+
+    int recv_sock = socket(AF_INET, SOCK_DGRAM, 0);
+    int send_sock = socket(AF_INET, SOCK_DGRAM, 0);
+    if (recv_sock < 0 || send_sock < 0) {
+        THROW_ERROR("create socket error");
+    }
+
+    struct sockaddr_in servaddr;
+    memset(&servaddr, 0, sizeof(servaddr));
+    servaddr.sin_family = AF_INET;
+    servaddr.sin_addr.s_addr = htonl(INADDR_LOOPBACK);
+    servaddr.sin_port = htons(8808);
+    ret = bind(recv_sock, (struct sockaddr *) &servaddr, sizeof(servaddr));
+    if (ret < 0) {
+        close(recv_sock);
+        close(send_sock);
+        THROW_ERROR("bind socket failed");
+    }
+
+    for (int i = 0; i < 2; i++) {
+        if (sendto(send_sock, DEFAULT_MSG, sizeof(DEFAULT_MSG), 0,
+                   (struct sockaddr *) &servaddr, sizeof(servaddr)) != sizeof(DEFAULT_MSG)) {
+            close(recv_sock);
+            close(send_sock);
+            THROW_ERROR("sendto failed");
+        }
+    }
+
+    memset(msgs, 0, sizeof(msgs));
+    for (int i = 0; i < 4; i++) {
+        iovs[i].iov_base = bufs[i];
+        iovs[i].iov_len = sizeof(bufs[i]);
+        msgs[i].msg_hdr.msg_iov = &iovs[i];
+        msgs[i].msg_hdr.msg_iovlen = 1;
+    }
+
+    // Only the two datagrams sent are received without blocking for the rest
+    ret = recvmmsg(recv_sock, msgs, 4, MSG_WAITFORONE, NULL);
+    if (ret != 2) {
+        close(recv_sock);
+        close(send_sock);
+        THROW_ERROR("recvmmsg should receive two datagrams");
+    }
+    for (int i = 0; i < ret; i++) {
+        if (msgs[i].msg_len != sizeof(DEFAULT_MSG) ||
+                strncmp(bufs[i], DEFAULT_MSG, sizeof(DEFAULT_MSG)) != 0) {
+            close(recv_sock);
+            close(send_sock);
+            THROW_ERROR("msg recvmmsg mismatch");
+        }
+    }
+
+    // An invalid timeout fails before any datagram is received
+    struct timespec timeout = { .tv_sec = 0, .tv_nsec = 1000000000 };
+    if (recvmmsg(recv_sock, msgs, 4, MSG_DONTWAIT, &timeout) != -1 || errno != EINVAL) {
+        close(recv_sock);
+        close(send_sock);
+        THROW_ERROR("recvmmsg should fail with an invalid timeout");
+    }
+
+    for (int i = 0; i < 2; i++) {
+        if (sendto(send_sock, DEFAULT_MSG, sizeof(DEFAULT_MSG), 0,
+                   (struct sockaddr *) &servaddr, sizeof(servaddr)) != sizeof(DEFAULT_MSG)) {
+            close(recv_sock);
+            close(send_sock);
+            THROW_ERROR("sendto failed");
+        }
+    }
+
+    // Like Linux, the timeout is checked after each datagram is received
+    timeout.tv_nsec = 0;
+    if (recvmmsg(recv_sock, msgs, 4, 0, &timeout) != 1) {
+        close(recv_sock);
+        close(send_sock);
+        THROW_ERROR("recvmmsg should stop after the timeout");
+    }
+    if (recvmmsg(recv_sock, msgs, 4, MSG_DONTWAIT, NULL) != 1) {
+        close(recv_sock);
+        close(send_sock);
+        THROW_ERROR("the rest of the datagrams should be kept");
+    }
+
+    close(recv_sock);
+    close(send_sock);
+    return 0;
+}
+
+int test_sendmmsg_connectionless() {
+    int ret = 0;
+    char buf[32];
+    struct iovec iov;
+    struct mmsghdr msgs[2];
+
+    int recv_sock = socket(AF_INET, SOCK_DGRAM, 0);
+    int send_sock = socket(AF_INET, SOCK_DGRAM, 0);
+    if (recv_sock < 0 || send_sock < 0) {
+        THROW_ERROR("create socket error");
+    }
+
+    struct sockaddr_in servaddr;
+    memset(&servaddr, 0, sizeof(servaddr));
+    servaddr.sin_family = AF_INET;
+    servaddr.sin_addr.s_addr = htonl(INADDR_LOOPBACK);
+    servaddr.sin_port = htons(8809);
+    ret = bind(recv_sock, (struct sockaddr *) &servaddr, sizeof(servaddr));
+    if (ret < 0) {
+        close(recv_sock);
+        close(send_sock);
+        THROW_ERROR("bind socket failed");
+    }
+
+    // Each datagram carries its own destination address
+    iov.iov_base = DEFAULT_MSG;
+    iov.iov_len = sizeof(DEFAULT_MSG);
+    memset(msgs, 0, sizeof(msgs));
+    for (int i = 0; i < 2; i++) {
+        msgs[i].msg_hdr.msg_name = &servaddr;
+        msgs[i].msg_hdr.msg_namelen = sizeof(servaddr);
+        msgs[i].msg_hdr.msg_iov = &iov;
+        msgs[i].msg_hdr.msg_iovlen = 1;
+    }
+
+    ret = sendmmsg(send_sock, msgs, 2, 0);
+    if (ret != 2 || msgs[0].msg_len != sizeof(DEFAULT_MSG) ||
+            msgs[1].msg_len != sizeof(DEFAULT_MSG)) {
+        close(recv_sock);
+        close(send_sock);
+        THROW_ERROR("sendmmsg should send two datagrams");
+    }
+
+    for (int i = 0; i < 2; i++) {
+        if (recv(recv_sock, buf, sizeof(buf), 0) != sizeof(DEFAULT_MSG) ||
+                strncmp(buf, DEFAULT_MSG, sizeof(DEFAULT_MSG)) != 0) {
+            close(recv_sock);
+            close(send_sock);
+            THROW_ERROR("msg sendmmsg mismatch");
+        }
+    }
+
+    close(recv_sock);
+    close(send_sock);
+    return 0;
+}
+
+int test_ioctl_fionbio() {
+    int sock = socket(AF_INET, SOCK_DGRAM, 0);
+    if (sock < 0) {
+        THROW_ERROR("create socket error");
+    }
+
+    struct sockaddr_in servaddr;
+    memset(&servaddr, 0, sizeof(servaddr));
+    servaddr.sin_family = AF_INET;
+    servaddr.sin_addr.s_addr = htonl(INADDR_LOOPBACK);
+    servaddr.sin_port = htons(8810);
+    if (bind(sock, (struct sockaddr *) &servaddr, sizeof(servaddr)) < 0) {
+        close(sock);
+        THROW_ERROR("bind error");
+    }
+
+    int on = 1;
+    if (ioctl(sock, FIONBIO, &on) < 0) {
+        close(sock);
+        THROW_ERROR("failed to set non-blocking mode with FIONBIO");
+    }
+    if ((fcntl(sock, F_GETFL, 0) & O_NONBLOCK) == 0) {
+        close(sock);
+        THROW_ERROR("FIONBIO should be seen by fcntl");
+    }
+    char buf[16];
+    if (recv(sock, buf, sizeof(buf), 0) >= 0 || errno != EAGAIN) {
+        close(sock);
+        THROW_ERROR("recv on an empty non-blocking socket should fail with EAGAIN");
+    }
+
+    int off = 0;
+    if (ioctl(sock, FIONBIO, &off) < 0) {
+        close(sock);
+        THROW_ERROR("failed to clear non-blocking mode with FIONBIO");
+    }
+    if ((fcntl(sock, F_GETFL, 0) & O_NONBLOCK) != 0) {
+        close(sock);
+        THROW_ERROR("FIONBIO should be seen by fcntl");
+    }
+
+    close(sock);
+    return 0;
+}
+
+int test_so_linger_abortive_close() {
+    int listen_fd = socket(AF_INET, SOCK_STREAM, 0);
+    if (listen_fd < 0) {
+        THROW_ERROR("create socket error");
+    }
+    int reuse = 1;
+    setsockopt(listen_fd, SOL_SOCKET, SO_REUSEADDR, &reuse, sizeof(reuse));
+
+    struct sockaddr_in servaddr;
+    memset(&servaddr, 0, sizeof(servaddr));
+    servaddr.sin_family = AF_INET;
+    servaddr.sin_addr.s_addr = htonl(INADDR_LOOPBACK);
+    servaddr.sin_port = htons(8811);
+    if (bind(listen_fd, (struct sockaddr *) &servaddr, sizeof(servaddr)) < 0 ||
+            listen(listen_fd, 5) < 0) {
+        close(listen_fd);
+        THROW_ERROR("failed to listen");
+    }
+
+    int client_fd = socket(AF_INET, SOCK_STREAM, 0);
+    if (client_fd < 0 ||
+            connect(client_fd, (struct sockaddr *) &servaddr, sizeof(servaddr)) < 0) {
+        close(listen_fd);
+        THROW_ERROR("failed to connect");
+    }
+    int accepted_fd = accept(listen_fd, NULL, NULL);
+    if (accepted_fd < 0) {
+        close(listen_fd);
+        close(client_fd);
+        THROW_ERROR("failed to accept");
+    }
+
+    struct linger linger = { .l_onoff = 1, .l_linger = 0 };
+    struct linger actual = { 0 };
+    socklen_t optlen = sizeof(actual);
+    if (setsockopt(client_fd, SOL_SOCKET, SO_LINGER, &linger, sizeof(linger)) < 0 ||
+            getsockopt(client_fd, SOL_SOCKET, SO_LINGER, &actual, &optlen) < 0 ||
+            actual.l_onoff == 0 || actual.l_linger != 0) {
+        close(listen_fd);
+        close(client_fd);
+        close(accepted_fd);
+        THROW_ERROR("failed to set SO_LINGER");
+    }
+
+    // A zero linger timeout resets the connection on close
+    close(client_fd);
+    char buf[16];
+    if (read(accepted_fd, buf, sizeof(buf)) != -1 || errno != ECONNRESET) {
+        close(listen_fd);
+        close(accepted_fd);
+        THROW_ERROR("the connection should be reset");
+    }
+
+    close(listen_fd);
+    close(accepted_fd);
+    return 0;
+}
+
+int test_oob_data() {
+    int listen_fd = socket(AF_INET, SOCK_STREAM, 0);
+    if (listen_fd < 0) {
+        THROW_ERROR("create socket error");
+    }
+    int reuse = 1;
+    setsockopt(listen_fd, SOL_SOCKET, SO_REUSEADDR, &reuse, sizeof(reuse));
+
+    struct sockaddr_in servaddr;
+    memset(&servaddr, 0, sizeof(servaddr));
+    servaddr.sin_family = AF_INET;
+    servaddr.sin_addr.s_addr = htonl(INADDR_LOOPBACK);
+    servaddr.sin_port = htons(8812);
+    if (bind(listen_fd, (struct sockaddr *) &servaddr, sizeof(servaddr)) < 0 ||
+            listen(listen_fd, 5) < 0) {
+        close(listen_fd);
+        THROW_ERROR("failed to listen");
+    }
+
+    int client_fd = socket(AF_INET, SOCK_STREAM, 0);
+    if (client_fd < 0 ||
+            connect(client_fd, (struct sockaddr *) &servaddr, sizeof(servaddr)) < 0) {
+        close(listen_fd);
+        THROW_ERROR("failed to connect");
+    }
+    int accepted_fd = accept(listen_fd, NULL, NULL);
+    if (accepted_fd < 0) {
+        close(listen_fd);
+        close(client_fd);
+        THROW_ERROR("failed to accept");
+    }
+
+    // The normal byte is before the urgent mark, which is at the urgent byte
+    if (send(client_fd, "a", 1, 0) != 1 || send(client_fd, "b", 1, MSG_OOB) != 1) {
+        close_files(3, listen_fd, client_fd, accepted_fd);
+        THROW_ERROR("failed to send");
+    }
+
+    struct pollfd polls[] = {
+        { .fd = accepted_fd, .events = POLLPRI }
+    };
+    if (poll(polls, 1, 1000) != 1 || !(polls[0].revents & POLLPRI)) {
+        close_files(3, listen_fd, client_fd, accepted_fd);
+        THROW_ERROR("urgent data should be reported by POLLPRI");
+    }
+
+    int atmark = -1;
+    if (ioctl(accepted_fd, SIOCATMARK, &atmark) < 0 || atmark != 0) {
+        close_files(3, listen_fd, client_fd, accepted_fd);
+        THROW_ERROR("the socket should not be at the mark");
+    }
+    char buf[16];
+    if (recv(accepted_fd, buf, sizeof(buf), 0) != 1 || buf[0] != 'a') {
+        close_files(3, listen_fd, client_fd, accepted_fd);
+        THROW_ERROR("failed to receive the normal data");
+    }
+    if (ioctl(accepted_fd, SIOCATMARK, &atmark) < 0 || atmark != 1) {
+        close_files(3, listen_fd, client_fd, accepted_fd);
+        THROW_ERROR("the socket should be at the mark");
+    }
+    if (recv(accepted_fd, buf, sizeof(buf), MSG_OOB) != 1 || buf[0] != 'b') {
+        close_files(3, listen_fd, client_fd, accepted_fd);
+        THROW_ERROR("failed to receive the urgent data");
+    }
+
+    close_files(3, listen_fd, client_fd, accepted_fd);
+    return 0;
+}
+
+int test_oob_inline() {
+    int listen_fd = socket(AF_INET, SOCK_STREAM, 0);
+    if (listen_fd < 0) {
+        THROW_ERROR("create socket error");
+    }
+    int reuse = 1;
+    setsockopt(listen_fd, SOL_SOCKET, SO_REUSEADDR, &reuse, sizeof(reuse));
+
+    struct sockaddr_in servaddr;
+    memset(&servaddr, 0, sizeof(servaddr));
+    servaddr.sin_family = AF_INET;
+    servaddr.sin_addr.s_addr = htonl(INADDR_LOOPBACK);
+    servaddr.sin_port = htons(8813);
+    if (bind(listen_fd, (struct sockaddr *) &servaddr, sizeof(servaddr)) < 0 ||
+            listen(listen_fd, 5) < 0) {
+        close(listen_fd);
+        THROW_ERROR("failed to listen");
+    }
+
+    int client_fd = socket(AF_INET, SOCK_STREAM, 0);
+    if (client_fd < 0 ||
+            connect(client_fd, (struct sockaddr *) &servaddr, sizeof(servaddr)) < 0) {
+        close(listen_fd);
+        THROW_ERROR("failed to connect");
+    }
+    int accepted_fd = accept(listen_fd, NULL, NULL);
+    if (accepted_fd < 0) {
+        close(listen_fd);
+        close(client_fd);
+        THROW_ERROR("failed to accept");
+    }
+
+    int oobinline = 1;
+    int actual = 0;
+    socklen_t optlen = sizeof(actual);
+    if (setsockopt(accepted_fd, SOL_SOCKET, SO_OOBINLINE, &oobinline, sizeof(oobinline)) < 0 ||
+            getsockopt(accepted_fd, SOL_SOCKET, SO_OOBINLINE, &actual, &optlen) < 0 ||
+            actual == 0) {
+        close_files(3, listen_fd, client_fd, accepted_fd);
+        THROW_ERROR("failed to set SO_OOBINLINE");
+    }
+
+    if (send(client_fd, "a", 1, 0) != 1 || send(client_fd, "b", 1, MSG_OOB) != 1) {
+        close_files(3, listen_fd, client_fd, accepted_fd);
+        THROW_ERROR("failed to send");
+    }
+
+    // The urgent byte is received in the normal stream
+    char buf[16];
+    int len = 0;
+    while (len < 2) {
+        int ret = recv(accepted_fd, buf + len, sizeof(buf) - len, 0);
+        if (ret <= 0) {
+            close_files(3, listen_fd, client_fd, accepted_fd);
+            THROW_ERROR("failed to receive");
+        }
+        len += ret;
+    }
+    if (len != 2 || strncmp(buf, "ab", 2) != 0) {
+        close_files(3, listen_fd, client_fd, accepted_fd);
+        THROW_ERROR("the urgent data should be inline");
+    }
+    if (recv(accepted_fd, buf, sizeof(buf), MSG_OOB) != -1 || errno != EINVAL) {
+        close_files(3, listen_fd, client_fd, accepted_fd);
+        THROW_ERROR("inline urgent data should not be received out of band");
+    }
+
+    close_files(3, listen_fd, client_fd, accepted_fd);
+    return 0;
+}
+
+int test_getsockname_getpeername() {
+    struct sockaddr_in addr;
+    socklen_t addr_len = sizeof(addr);
+
+    // An unbound socket has the wildcard address
+    int udp_fd = socket(AF_INET, SOCK_DGRAM, 0);
+    if (udp_fd < 0) {
+        THROW_ERROR("create socket error");
+    }
+    if (getsockname(udp_fd, (struct sockaddr *) &addr, &addr_len) < 0 ||
+            addr_len != sizeof(addr) || addr.sin_family != AF_INET ||
+            addr.sin_addr.s_addr != htonl(INADDR_ANY) || addr.sin_port != 0) {
+        close(udp_fd);
+        THROW_ERROR("unbound socket should have the wildcard address");
+    }
+
+    // Connecting binds the socket and sets the peer
+    struct sockaddr_in peer;
+    memset(&peer, 0, sizeof(peer));
+    peer.sin_family = AF_INET;
+    peer.sin_addr.s_addr = htonl(INADDR_LOOPBACK);
+    peer.sin_port = htons(8814);
+    if (connect(udp_fd, (struct sockaddr *) &peer, sizeof(peer)) < 0) {
+        close(udp_fd);
+        THROW_ERROR("failed to connect");
+    }
+    addr_len = sizeof(addr);
+    if (getsockname(udp_fd, (struct sockaddr *) &addr, &addr_len) < 0 || addr.sin_port == 0) {
+        close(udp_fd);
+        THROW_ERROR("connected socket should be bound");
+    }
+    addr_len = sizeof(addr);
+    if (getpeername(udp_fd, (struct sockaddr *) &addr, &addr_len) < 0 ||
+            addr.sin_port != htons(8814)) {
+        close(udp_fd);
+        THROW_ERROR("failed to get the peer address");
+    }
+
+    // Connecting again changes the peer
+    peer.sin_port = htons(8815);
+    if (connect(udp_fd, (struct sockaddr *) &peer, sizeof(peer)) < 0) {
+        close(udp_fd);
+        THROW_ERROR("failed to connect");
+    }
+    addr_len = sizeof(addr);
+    if (getpeername(udp_fd, (struct sockaddr *) &addr, &addr_len) < 0 ||
+            addr.sin_port != htons(8815)) {
+        close(udp_fd);
+        THROW_ERROR("the peer address should be updated");
+    }
+
+    // A short buffer gets a truncated address and the actual length
+    addr_len = sizeof(sa_family_t);
+    if (getpeername(udp_fd, (struct sockaddr *) &addr, &addr_len) < 0 ||
+            addr_len != sizeof(addr)) {
+        close(udp_fd);
+        THROW_ERROR("the actual length of the address should be returned");
+    }
+
+    close(udp_fd);
+    return 0;
+}
+
+int test_udp_disconnect() {
+    int udp_fd = socket(AF_INET, SOCK_DGRAM, 0);
+    if (udp_fd < 0) {
+        THROW_ERROR("create socket error");
+    }
+
+    struct sockaddr_in peer;
+    memset(&peer, 0, sizeof(peer));
+    peer.sin_family = AF_INET;
+    peer.sin_addr.s_addr = htonl(INADDR_LOOPBACK);
+    peer.sin_port = htons(8816);
+    if (connect(udp_fd, (struct sockaddr *) &peer, sizeof(peer)) < 0) {
+        close(udp_fd);
+        THROW_ERROR("failed to connect");
+    }
+    struct sockaddr_in addr;
+    socklen_t addr_len = sizeof(addr);
+    if (getpeername(udp_fd, (struct sockaddr *) &addr, &addr_len) < 0) {
+        close(udp_fd);
+        THROW_ERROR("failed to get the peer address");
+    }
+
+    // Connecting to an AF_UNSPEC address dissolves the association
+    struct sockaddr unspec;
+    memset(&unspec, 0, sizeof(unspec));
+    unspec.sa_family = AF_UNSPEC;
+    if (connect(udp_fd, &unspec, sizeof(unspec)) < 0) {
+        close(udp_fd);
+        THROW_ERROR("failed to disconnect");
+    }
+    addr_len = sizeof(addr);
+    if (getpeername(udp_fd, (struct sockaddr *) &addr, &addr_len) != -1 || errno != ENOTCONN) {
+        close(udp_fd);
+        THROW_ERROR("a disconnected socket should have no peer");
+    }
+    if (send(udp_fd, "a", 1, 0) != -1 || errno != EDESTADDRREQ) {
+        close(udp_fd);
+        THROW_ERROR("sending without a destination should fail");
+    }
+
+    close(udp_fd);
+    return 0;
+}
+
+int test_so_broadcast() {
+    int udp_fd = socket(AF_INET, SOCK_DGRAM, 0);
+    if (udp_fd < 0) {
+        THROW_ERROR("create socket error");
+    }
+
+    struct sockaddr_in broadcast_addr;
+    memset(&broadcast_addr, 0, sizeof(broadcast_addr));
+    broadcast_addr.sin_family = AF_INET;
+    broadcast_addr.sin_addr.s_addr = htonl(INADDR_BROADCAST);
+    broadcast_addr.sin_port = htons(8817);
+
+    // Broadcasting is disabled by default
+    int broadcast = -1;
+    socklen_t optlen = sizeof(broadcast);
+    if (getsockopt(udp_fd, SOL_SOCKET, SO_BROADCAST, &broadcast, &optlen) < 0 ||
+            broadcast != 0) {
+        close(udp_fd);
+        THROW_ERROR("SO_BROADCAST should be disabled by default");
+    }
+    if (sendto(udp_fd, "a", 1, 0, (struct sockaddr *) &broadcast_addr,
+               sizeof(broadcast_addr)) != -1 || errno != EACCES) {
+        close(udp_fd);
+        THROW_ERROR("broadcasting without SO_BROADCAST should fail with EACCES");
+    }
+
+    broadcast = 1;
+    if (setsockopt(udp_fd, SOL_SOCKET, SO_BROADCAST, &broadcast, sizeof(broadcast)) < 0) {
+        close(udp_fd);
+        THROW_ERROR("failed to set SO_BROADCAST");
+    }
+    broadcast = 0;
+    optlen = sizeof(broadcast);
+    if (getsockopt(udp_fd, SOL_SOCKET, SO_BROADCAST, &broadcast, &optlen) < 0 ||
+            broadcast == 0) {
+        close(udp_fd);
+        THROW_ERROR("SO_BROADCAST should be enabled");
+    }
+    // The send may still fail if there is no route for broadcasting
+    if (sendto(udp_fd, "a", 1, 0, (struct sockaddr *) &broadcast_addr,
+               sizeof(broadcast_addr)) < 0 && errno == EACCES) {
+        close(udp_fd);
+        THROW_ERROR("broadcasting with SO_BROADCAST should be permitted");
+    }
+
+    close(udp_fd);
+    return 0;
+}
+
+int test_ip_multicast() {
+    int udp_fd = socket(AF_INET, SOCK_DGRAM, 0);
+    if (udp_fd < 0) {
+        THROW_ERROR("create socket error");
+    }
+    int reuse = 1;
+    setsockopt(udp_fd, SOL_SOCKET, SO_REUSEADDR, &reuse, sizeof(reuse));
+
+    struct sockaddr_in addr;
+    memset(&addr, 0, sizeof(addr));
+    addr.sin_family = AF_INET;
+    addr.sin_addr.s_addr = htonl(INADDR_ANY);
+    addr.sin_port = htons(8818);
+    if (bind(udp_fd, (struct sockaddr *) &addr, sizeof(addr)) < 0) {
+        close(udp_fd);
+        THROW_ERROR("failed to bind");
+    }
+
+    struct ip_mreq mreq;
+    mreq.imr_multiaddr.s_addr = inet_addr("239.1.2.3");
+    mreq.imr_interface.s_addr = htonl(INADDR_LOOPBACK);
+    if (setsockopt(udp_fd, IPPROTO_IP, IP_DROP_MEMBERSHIP, &mreq, sizeof(mreq)) != -1 ||
+            errno != EADDRNOTAVAIL) {
+        close(udp_fd);
+        THROW_ERROR("dropping a group not joined should fail with EADDRNOTAVAIL");
+    }
+    if (setsockopt(udp_fd, IPPROTO_IP, IP_ADD_MEMBERSHIP, &mreq, sizeof(mreq)) < 0) {
+        close(udp_fd);
+        THROW_ERROR("failed to join the multicast group");
+    }
+
+    unsigned char ttl = 1;
+    unsigned char loop = 1;
+    struct in_addr interface = { .s_addr = htonl(INADDR_LOOPBACK) };
+    if (setsockopt(udp_fd, IPPROTO_IP, IP_MULTICAST_TTL, &ttl, sizeof(ttl)) < 0 ||
+            setsockopt(udp_fd, IPPROTO_IP, IP_MULTICAST_LOOP, &loop, sizeof(loop)) < 0 ||
+            setsockopt(udp_fd, IPPROTO_IP, IP_MULTICAST_IF, &interface, sizeof(interface)) < 0) {
+        close(udp_fd);
+        THROW_ERROR("failed to set the multicast options");
+    }
+
+    // The datagram sent to the group is looped back to the member
+    struct sockaddr_in group_addr = addr;
+    group_addr.sin_addr = mreq.imr_multiaddr;
+    if (sendto(udp_fd, "hello", 5, 0, (struct sockaddr *) &group_addr,
+               sizeof(group_addr)) != 5) {
+        close(udp_fd);
+        THROW_ERROR("failed to send to the multicast group");
+    }
+    char buf[16] = {0};
+    struct pollfd polls[] = {
+        { .fd = udp_fd, .events = POLLIN }
+    };
+    if (poll(polls, 1, 1000) != 1 || recv(udp_fd, buf, sizeof(buf), 0) != 5 ||
+            strncmp(buf, "hello", 5) != 0) {
+        close(udp_fd);
+        THROW_ERROR("failed to receive the multicast datagram");
+    }
+
+    if (setsockopt(udp_fd, IPPROTO_IP, IP_DROP_MEMBERSHIP, &mreq, sizeof(mreq)) < 0) {
+        close(udp_fd);
+        THROW_ERROR("failed to drop the multicast group");
+    }
+
+    close(udp_fd);
+    return 0;
+}
+
+int test_tcp_cork_and_msg_more() {
+    int listen_fd = socket(AF_INET, SOCK_STREAM, 0);
+    if (listen_fd < 0) {
+        THROW_ERROR("create socket error");
+    }
+    int reuse = 1;
+    setsockopt(listen_fd, SOL_SOCKET, SO_REUSEADDR, &reuse, sizeof(reuse));
+
+    struct sockaddr_in servaddr;
+    memset(&servaddr, 0, sizeof(servaddr));
+    servaddr.sin_family = AF_INET;
+    servaddr.sin_addr.s_addr = htonl(INADDR_LOOPBACK);
+    servaddr.sin_port = htons(8819);
+    if (bind(listen_fd, (struct sockaddr *) &servaddr, sizeof(servaddr)) < 0 ||
+            listen(listen_fd, 5) < 0) {
+        close(listen_fd);
+        THROW_ERROR("failed to listen");
+    }
+
+    int client_fd = socket(AF_INET, SOCK_STREAM, 0);
+    if (client_fd < 0 ||
+            connect(client_fd, (struct sockaddr *) &servaddr, sizeof(servaddr)) < 0) {
+        close(listen_fd);
+        THROW_ERROR("failed to connect");
+    }
+    int accepted_fd = accept(listen_fd, NULL, NULL);
+    if (accepted_fd < 0) {
+        close(listen_fd);
+        close(client_fd);
+        THROW_ERROR("failed to accept");
+    }
+
+    int cork = 1;
+    int actual = 0;
+    socklen_t optlen = sizeof(actual);
+    if (setsockopt(client_fd, IPPROTO_TCP, TCP_CORK, &cork, sizeof(cork)) < 0 ||
+            getsockopt(client_fd, IPPROTO_TCP, TCP_CORK, &actual, &optlen) < 0 ||
+            actual != 1) {
+        close_files(3, listen_fd, client_fd, accepted_fd);
+        THROW_ERROR("failed to set TCP_CORK");
+    }
+
+    // The corked data is sent once the socket is uncorked
+    cork = 0;
+    if (send(client_fd, "head", 4, 0) != 4 || send(client_fd, "body", 4, MSG_MORE) != 4 ||
+            setsockopt(client_fd, IPPROTO_TCP, TCP_CORK, &cork, sizeof(cork)) < 0) {
+        close_files(3, listen_fd, client_fd, accepted_fd);
+        THROW_ERROR("failed to send corked data");
+    }
+
+    // The data held back by MSG_MORE is sent when the socket is closed
+    if (send(client_fd, "tail", 4, MSG_MORE) != 4) {
+        close_files(3, listen_fd, client_fd, accepted_fd);
+        THROW_ERROR("failed to send with MSG_MORE");
+    }
+    close(client_fd);
+
+    char buf[32];
+    int len = 0;
+    while (len < sizeof(buf)) {
+        int ret = recv(accepted_fd, buf + len, sizeof(buf) - len, 0);
+        if (ret < 0) {
+            close_files(2, listen_fd, accepted_fd);
+            THROW_ERROR("failed to receive");
+        }
+        if (ret == 0) {
+            break;
+        }
+        len += ret;
+    }
+    if (len != 12 || strncmp(buf, "headbodytail", 12) != 0) {
+        close_files(2, listen_fd, accepted_fd);
+        THROW_ERROR("all the data should be received");
+    }
+
+    close_files(2, listen_fd, accepted_fd);
+    return 0;
+}
+
+int test_so_bindtodevice() {
+    int fd = socket(AF_INET, SOCK_DGRAM, 0);
+    if (fd < 0) {
+        THROW_ERROR("create socket error");
+    }
+
+    const char *dev = "lo";
+    if (setsockopt(fd, SOL_SOCKET, SO_BINDTODEVICE, dev, strlen(dev) + 1) < 0) {
+        close(fd);
+        THROW_ERROR("failed to bind to the loopback device");
+    }
+
+    char name[IFNAMSIZ] = {0};
+    socklen_t optlen = sizeof(name);
+    if (getsockopt(fd, SOL_SOCKET, SO_BINDTODEVICE, name, &optlen) < 0 ||
+            strcmp(name, dev) != 0) {
+        close(fd);
+        THROW_ERROR("the bound device should be returned");
+    }
+
+    const char *bad_dev = "nosuchdev0";
+    if (setsockopt(fd, SOL_SOCKET, SO_BINDTODEVICE, bad_dev, strlen(bad_dev) + 1) != -1 ||
+            errno != ENODEV) {
+        close(fd);
+        THROW_ERROR("binding to a nonexistent device should fail with ENODEV");
+    }
+
+    close(fd);
+    return 0;
+}
+
+int test_dup_socket() {
+    int fds[2];
+    if (tcp_socket_pair(fds) < 0) {
+        THROW_ERROR("failed to create a pair of connected sockets");
+    }
+    int client_fd = fds[0];
+    int accepted_fd = fds[1];
+    int dup_fd = dup(client_fd);
+    if (dup_fd < 0) {
+        close_files(2, client_fd, accepted_fd);
+        THROW_ERROR("failed to dup the socket");
+    }
+
+    // The status flags are shared by the duplicated fds, but the fd flags are not
+    int flags = fcntl(dup_fd, F_GETFL, 0);
+    if (fcntl(dup_fd, F_SETFL, flags | O_NONBLOCK) < 0 ||
+            !(fcntl(client_fd, F_GETFL, 0) & O_NONBLOCK)) {
+        close_files(3, client_fd, accepted_fd, dup_fd);
+        THROW_ERROR("the status flags should be shared");
+    }
+    if (fcntl(dup_fd, F_SETFD, FD_CLOEXEC) < 0 ||
+            (fcntl(client_fd, F_GETFD, 0) & FD_CLOEXEC)) {
+        close_files(3, client_fd, accepted_fd, dup_fd);
+        THROW_ERROR("the fd flags should not be shared");
+    }
+
+    // Closing one fd keeps the connection alive
+    close(client_fd);
+    if (send(dup_fd, "a", 1, 0) != 1) {
+        close_files(2, accepted_fd, dup_fd);
+        THROW_ERROR("failed to send through the duplicated fd");
+    }
+    char buf[16];
+    if (recv(accepted_fd, buf, sizeof(buf), 0) != 1 || buf[0] != 'a') {
+        close_files(2, accepted_fd, dup_fd);
+        THROW_ERROR("failed to receive the data sent through the duplicated fd");
+    }
+
+    // Closing the last fd closes the connection
+    close(dup_fd);
+    if (recv(accepted_fd, buf, sizeof(buf), 0) != 0) {
+        close(accepted_fd);
+        THROW_ERROR("the connection should be closed with the last fd");
+    }
+
+    close(accepted_fd);
+    return 0;
+}
+
+int test_tcp_info() {
+    int listen_fd = socket(AF_INET, SOCK_STREAM, 0);
+    if (listen_fd < 0) {
+        THROW_ERROR("create socket error");
+    }
+    int reuse = 1;
+    setsockopt(listen_fd, SOL_SOCKET, SO_REUSEADDR, &reuse, sizeof(reuse));
+
+    struct sockaddr_in servaddr;
+    memset(&servaddr, 0, sizeof(servaddr));
+    servaddr.sin_family = AF_INET;
+    servaddr.sin_addr.s_addr = htonl(INADDR_LOOPBACK);
+    servaddr.sin_port = htons(8821);
+    if (bind(listen_fd, (struct sockaddr *) &servaddr, sizeof(servaddr)) < 0 ||
+            listen(listen_fd, 5) < 0) {
+        close(listen_fd);
+        THROW_ERROR("failed to listen");
+    }
+
+    int client_fd = socket(AF_INET, SOCK_STREAM, 0);
+    if (client_fd < 0 ||
+            connect(client_fd, (struct sockaddr *) &servaddr, sizeof(servaddr)) < 0) {
+        close(listen_fd);
+        THROW_ERROR("failed to connect");
+    }
+
+    struct tcp_info info;
+    socklen_t optlen = sizeof(info);
+    memset(&info, 0, sizeof(info));
+    if (getsockopt(client_fd, IPPROTO_TCP, TCP_INFO, &info, &optlen) < 0 ||
+            optlen == 0 || info.tcpi_state != TCP_ESTABLISHED) {
+        close_files(2, listen_fd, client_fd);
+        THROW_ERROR("failed to get the info of an established connection");
+    }
+    if (setsockopt(client_fd, IPPROTO_TCP, TCP_INFO, &info, sizeof(info)) != -1 ||
+            errno != ENOPROTOOPT) {
+        close_files(2, listen_fd, client_fd);
+        THROW_ERROR("TCP_INFO should be read-only");
+    }
+
+    int udp_fd = socket(AF_INET, SOCK_DGRAM, 0);
+    optlen = sizeof(info);
+    if (udp_fd < 0 ||
+            getsockopt(udp_fd, IPPROTO_TCP, TCP_INFO, &info, &optlen) != -1 ||
+            errno != EOPNOTSUPP) {
+        close_files(3, listen_fd, client_fd, udp_fd);
+        THROW_ERROR("TCP_INFO should not be supported by UDP sockets");
+    }
+
+    close_files(3, listen_fd, client_fd, udp_fd);
+    return 0;
+}
+
+int test_ioctl_queue_len() {
+    int listen_fd = socket(AF_INET, SOCK_STREAM, 0);
+    if (listen_fd < 0) {
+        THROW_ERROR("create socket error");
+    }
+    int reuse = 1;
+    setsockopt(listen_fd, SOL_SOCKET, SO_REUSEADDR, &reuse, sizeof(reuse));
+
+    struct sockaddr_in servaddr;
+    memset(&servaddr, 0, sizeof(servaddr));
+    servaddr.sin_family = AF_INET;
+    servaddr.sin_addr.s_addr = htonl(INADDR_LOOPBACK);
+    servaddr.sin_port = htons(8822);
+    if (bind(listen_fd, (struct sockaddr *) &servaddr, sizeof(servaddr)) < 0 ||
+            listen(listen_fd, 5) < 0) {
+        close(listen_fd);
+        THROW_ERROR("failed to listen");
+    }
+
+    int client_fd = socket(AF_INET, SOCK_STREAM | SOCK_NONBLOCK, 0);
+    if (client_fd < 0) {
+        close(listen_fd);
+        THROW_ERROR("create socket error");
+    }
+    if (connect(client_fd, (struct sockaddr *) &servaddr, sizeof(servaddr)) < 0 &&
+            errno != EINPROGRESS) {
+        close_files(2, listen_fd, client_fd);
+        THROW_ERROR("failed to connect");
+    }
+    int accepted_fd = accept(listen_fd, NULL, NULL);
+    if (accepted_fd < 0) {
+        close_files(2, listen_fd, client_fd);
+        THROW_ERROR("failed to accept");
+    }
+
+    // Send without draining until the buffers of both ends are full, so that
+    // the data queued for sending can no longer be delivered
+    char buf[4096] = {0};
+    int total_len = 0;
+    while (1) {
+        int ret = send(client_fd, buf, sizeof(buf), 0);
+        if (ret < 0) {
+            if (errno == EAGAIN) {
+                break;
+            }
+            close_files(3, listen_fd, client_fd, accepted_fd);
+            THROW_ERROR("failed to send");
+        }
+        total_len += ret;
+    }
+
+    int outq = -1;
+    int inq = -1;
+    if (ioctl(client_fd, SIOCOUTQ, &outq) < 0 || outq <= 0 || outq > total_len) {
+        close_files(3, listen_fd, client_fd, accepted_fd);
+        THROW_ERROR("the output queue should hold the unsent data");
+    }
+    if (ioctl(accepted_fd, SIOCINQ, &inq) < 0 || inq <= 0 || inq > total_len) {
+        close_files(3, listen_fd, client_fd, accepted_fd);
+        THROW_ERROR("the input queue should hold the unread data");
+    }
+
+    close_files(3, listen_fd, client_fd, accepted_fd);
+    return 0;
+}
+
+static int check_socket_info(int fd, int type, int domain, int protocol, int acceptconn) {
+    int optnames[4] = {SO_TYPE, SO_DOMAIN, SO_PROTOCOL, SO_ACCEPTCONN};
+    int expected[4] = {type, domain, protocol, acceptconn};
+    for (int i = 0; i < 4; i++) {
+        int val = -1;
+        socklen_t optlen = sizeof(val);
+        if (getsockopt(fd, SOL_SOCKET, optnames[i], &val, &optlen) < 0 ||
+                optlen != sizeof(val) || val != expected[i]) {
+            THROW_ERROR("the socket info of option %d is wrong", optnames[i]);
+        }
+    }
+    return 0;
+}
+
+int test_socket_info() {
+    int tcp_fd = socket(AF_INET, SOCK_STREAM, 0);
+    int udp_fd = socket(AF_INET, SOCK_DGRAM, 0);
+    if (tcp_fd < 0 || udp_fd < 0) {
+        close_files(2, tcp_fd, udp_fd);
+        THROW_ERROR("create socket error");
+    }
+    if (check_socket_info(tcp_fd, SOCK_STREAM, AF_INET, IPPROTO_TCP, 0) < 0 ||
+            check_socket_info(udp_fd, SOCK_DGRAM, AF_INET, IPPROTO_UDP, 0) < 0) {
+        close_files(2, tcp_fd, udp_fd);
+        THROW_ERROR("wrong info of inet sockets");
+    }
+
+    struct sockaddr_in addr;
+    memset(&addr, 0, sizeof(addr));
+    addr.sin_family = AF_INET;
+    addr.sin_addr.s_addr = htonl(INADDR_LOOPBACK);
+    addr.sin_port = htons(0);
+    if (bind(tcp_fd, (struct sockaddr *) &addr, sizeof(addr)) < 0 ||
+            listen(tcp_fd, 5) < 0 ||
+            check_socket_info(tcp_fd, SOCK_STREAM, AF_INET, IPPROTO_TCP, 1) < 0) {
+        close_files(2, tcp_fd, udp_fd);
+        THROW_ERROR("a listening socket should accept connections");
+    }
+
+    close_files(2, tcp_fd, udp_fd);
+    return 0;
+}
+
+int test_ip_tos_and_ttl() {
+    int fd = socket(AF_INET, SOCK_DGRAM, 0);
+    if (fd < 0) {
+        THROW_ERROR("create socket error");
+    }
+
+    int ttl = 5;
+    int tos = 0x10;
+    int val = 0;
+    socklen_t optlen = sizeof(val);
+    if (setsockopt(fd, IPPROTO_IP, IP_TTL, &ttl, sizeof(ttl)) < 0 ||
+            getsockopt(fd, IPPROTO_IP, IP_TTL, &val, &optlen) < 0 || val != ttl) {
+        close(fd);
+        THROW_ERROR("failed to set IP_TTL");
+    }
+    optlen = sizeof(val);
+    if (setsockopt(fd, IPPROTO_IP, IP_TOS, &tos, sizeof(tos)) < 0 ||
+            getsockopt(fd, IPPROTO_IP, IP_TOS, &val, &optlen) < 0 || val != tos) {
+        close(fd);
+        THROW_ERROR("failed to set IP_TOS");
+    }
+
+    ttl = 0;
+    if (setsockopt(fd, IPPROTO_IP, IP_TTL, &ttl, sizeof(ttl)) != -1 || errno != EINVAL) {
+        close(fd);
+        THROW_ERROR("a zero TTL should be rejected");
+    }
+    close(fd);
+
+    fd = socket(AF_INET6, SOCK_DGRAM, 0);
+    if (fd < 0) {
+        THROW_ERROR("create socket error");
+    }
+    int hops = 7;
+    optlen = sizeof(val);
+    if (setsockopt(fd, IPPROTO_IPV6, IPV6_UNICAST_HOPS, &hops, sizeof(hops)) < 0 ||
+            getsockopt(fd, IPPROTO_IPV6, IPV6_UNICAST_HOPS, &val, &optlen) < 0 ||
+            val != hops) {
+        close(fd);
+        THROW_ERROR("failed to set IPV6_UNICAST_HOPS");
+    }
+    hops = 256;
+    if (setsockopt(fd, IPPROTO_IPV6, IPV6_UNICAST_HOPS, &hops, sizeof(hops)) != -1 ||
+            errno != EINVAL) {
+        close(fd);
+        THROW_ERROR("an out-of-range hop limit should be rejected");
+    }
+
+    close(fd);
+    return 0;
+}
+
+int test_stream_pair_checks() {
+    return run_stream_pair_checks(tcp_socket_pair);
+}
+
+int test_so_reuseport() {
+    struct sockaddr_in servaddr;
+    memset(&servaddr, 0, sizeof(servaddr));
+    servaddr.sin_family = AF_INET;
+    servaddr.sin_addr.s_addr = htonl(INADDR_LOOPBACK);
+    servaddr.sin_port = htons(8824);
+
+    // Both sockets with SO_REUSEPORT can listen on the same port
+    int listen_fds[2];
+    int reuse = 1;
+    for (int i = 0; i < 2; i++) {
+        listen_fds[i] = socket(AF_INET, SOCK_STREAM, 0);
+        if (listen_fds[i] < 0 ||
+                setsockopt(listen_fds[i], SOL_SOCKET, SO_REUSEPORT, &reuse, sizeof(reuse)) < 0 ||
+                bind(listen_fds[i], (struct sockaddr *) &servaddr, sizeof(servaddr)) < 0 ||
+                listen(listen_fds[i], 5) < 0) {
+            if (i == 1) {
+                close(listen_fds[0]);
+            }
+            close(listen_fds[i]);
+            THROW_ERROR("failed to listen with SO_REUSEPORT");
+        }
+    }
+
+    int val = 0;
+    socklen_t optlen = sizeof(val);
+    if (getsockopt(listen_fds[1], SOL_SOCKET, SO_REUSEPORT, &val, &optlen) < 0 || val != 1) {
+        close_files(2, listen_fds[0], listen_fds[1]);
+        THROW_ERROR("SO_REUSEPORT should be set");
+    }
+
+    // A socket without SO_REUSEPORT cannot bind the port
+    int fd = socket(AF_INET, SOCK_STREAM, 0);
+    if (fd < 0) {
+        close_files(2, listen_fds[0], listen_fds[1]);
+        THROW_ERROR("create socket error");
+    }
+    if (bind(fd, (struct sockaddr *) &servaddr, sizeof(servaddr)) != -1 || errno != EADDRINUSE) {
+        close_files(3, listen_fds[0], listen_fds[1], fd);
+        THROW_ERROR("binding without SO_REUSEPORT should fail with EADDRINUSE");
+    }
+
+    // The connection is accepted by one of the listening sockets
+    if (connect(fd, (struct sockaddr *) &servaddr, sizeof(servaddr)) < 0) {
+        close_files(3, listen_fds[0], listen_fds[1], fd);
+        THROW_ERROR("failed to connect");
+    }
+    struct pollfd pfds[2] = {
+        { .fd = listen_fds[0], .events = POLLIN },
+        { .fd = listen_fds[1], .events = POLLIN },
+    };
+    if (poll(pfds, 2, 1000) != 1) {
+        close_files(3, listen_fds[0], listen_fds[1], fd);
+        THROW_ERROR("exactly one listening socket should get the connection");
+    }
+    int accepted_fd = accept(pfds[0].revents & POLLIN ? listen_fds[0] : listen_fds[1], NULL, NULL);
+    if (accepted_fd < 0) {
+        close_files(3, listen_fds[0], listen_fds[1], fd);
+        THROW_ERROR("failed to accept");
+    }
+
+    close_files(4, listen_fds[0], listen_fds[1], fd, accepted_fd);
+    return 0;
+}
+
+int test_accept_peer_addr() {
+    struct sockaddr_in servaddr;
+    memset(&servaddr, 0, sizeof(servaddr));
+    servaddr.sin_family = AF_INET;
+    servaddr.sin_addr.s_addr = htonl(INADDR_LOOPBACK);
+    servaddr.sin_port = htons(8825);
+
+    int listen_fd = socket(AF_INET, SOCK_STREAM, 0);
+    if (listen_fd < 0 ||
+            bind(listen_fd, (struct sockaddr *) &servaddr, sizeof(servaddr)) < 0 ||
+            listen(listen_fd, 5) < 0) {
+        THROW_ERROR("failed to listen");
+    }
+
+    int client_fd = socket(AF_INET, SOCK_STREAM, 0);
+    if (client_fd < 0 ||
+            connect(client_fd, (struct sockaddr *) &servaddr, sizeof(servaddr)) < 0) {
+        close(listen_fd);
+        THROW_ERROR("failed to connect");
+    }
+    struct sockaddr_in client_addr;
+    socklen_t client_addr_len = sizeof(client_addr);
+    if (getsockname(client_fd, (struct sockaddr *) &client_addr, &client_addr_len) < 0) {
+        close_files(2, listen_fd, client_fd);
+        THROW_ERROR("failed to get the client address");
+    }
+
+    // The address returned by accept is the address of the client
+    struct sockaddr_in peer_addr;
+    memset(&peer_addr, 0, sizeof(peer_addr));
+    socklen_t peer_addr_len = sizeof(peer_addr);
+    int accepted_fd = accept(listen_fd, (struct sockaddr *) &peer_addr, &peer_addr_len);
+    if (accepted_fd < 0) {
+        close_files(2, listen_fd, client_fd);
+        THROW_ERROR("failed to accept");
+    }
+    if (peer_addr_len != sizeof(peer_addr) || peer_addr.sin_family != AF_INET ||
+            peer_addr.sin_addr.s_addr != client_addr.sin_addr.s_addr ||
+            peer_addr.sin_port != client_addr.sin_port) {
+        close_files(3, listen_fd, client_fd, accepted_fd);
+        THROW_ERROR("the peer address returned by accept is wrong");
+    }
+
+    // A short buffer gets the truncated address and the full length
+    int short_fd = socket(AF_INET, SOCK_STREAM, 0);
+    if (short_fd < 0 ||
+            connect(short_fd, (struct sockaddr *) &servaddr, sizeof(servaddr)) < 0) {
+        close_files(3, listen_fd, client_fd, accepted_fd);
+        THROW_ERROR("failed to connect");
+    }
+    sa_family_t family = 0;
+    peer_addr_len = sizeof(family);
+    int short_accepted_fd = accept(listen_fd, (struct sockaddr *) &family, &peer_addr_len);
+    close_files(5, listen_fd, client_fd, accepted_fd, short_fd, short_accepted_fd);
+    if (short_accepted_fd < 0) {
+        THROW_ERROR("failed to accept");
+    }
+    if (family != AF_INET || peer_addr_len != sizeof(peer_addr)) {
+        THROW_ERROR("the truncated peer address is wrong");
+    }
+
+    return 0;
+}
+
+int test_sendfile_to_socket() {
+    const char *file_path = "/tmp/sendfile_to_socket.txt";
+    const char *file_data = "0123456789abcdefghijklmnopqrstuvwxyz";
+    size_t data_len = strlen(file_data);
+    int file_fd = open(file_path, O_RDWR | O_CREAT | O_TRUNC, 00666);
+    if (file_fd < 0) {
+        THROW_ERROR("failed to create the file");
+    }
+    if (write(file_fd, file_data, data_len) != data_len || lseek(file_fd, 0, SEEK_SET) != 0) {
+        close(file_fd);
+        THROW_ERROR("failed to write the file");
+    }
+
+    int fds[2];
+    if (tcp_socket_pair(fds) < 0) {
+        close(file_fd);
+        THROW_ERROR("failed to create a pair of connected sockets");
+    }
+    int client_fd = fds[0];
+    int accepted_fd = fds[1];
+
+    // Sending from an offset updates the offset but not the file position
+    char buf[64] = {0};
+    off_t offset = 10;
+    if (sendfile(client_fd, file_fd, &offset, 5) != 5 || offset != 15 ||
+            lseek(file_fd, 0, SEEK_CUR) != 0) {
+        close_files(3, file_fd, client_fd, accepted_fd);
+        THROW_ERROR("failed to sendfile from an offset");
+    }
+    if (recv(accepted_fd, buf, 5, MSG_WAITALL) != 5 ||
+            strncmp(buf, file_data + 10, 5) != 0) {
+        close_files(3, file_fd, client_fd, accepted_fd);
+        THROW_ERROR("the data sent from an offset is wrong");
+    }
+
+    // Sending without an offset advances the file position, and stops at the end of file
+    if (sendfile(client_fd, file_fd, NULL, sizeof(buf)) != data_len ||
+            lseek(file_fd, 0, SEEK_CUR) != data_len) {
+        close_files(3, file_fd, client_fd, accepted_fd);
+        THROW_ERROR("failed to sendfile from the file position");
+    }
+    memset(buf, 0, sizeof(buf));
+    if (recv(accepted_fd, buf, data_len, MSG_WAITALL) != data_len ||
+            strncmp(buf, file_data, data_len) != 0) {
+        close_files(3, file_fd, client_fd, accepted_fd);
+        THROW_ERROR("the data sent from the file position is wrong");
+    }
+    if (sendfile(client_fd, file_fd, NULL, sizeof(buf)) != 0) {
+        close_files(3, file_fd, client_fd, accepted_fd);
+        THROW_ERROR("sendfile at the end of file should send nothing");
+    }
+
+    close_files(3, file_fd, client_fd, accepted_fd);
+    if (unlink(file_path) < 0) {
+        THROW_ERROR("failed to unlink the file");
+    }
+    return 0;
+}
+
+// The shutdown of a TCP connection is covered by test_stream_pair_checks
+int test_poll_hup_on_reset() {
+    int fds[2];
+    if (tcp_socket_pair(fds) < 0) {
+        THROW_ERROR("failed to create a pair of connected sockets");
+    }
+    struct sockaddr_in peer;
+    socklen_t addr_len = sizeof(peer);
+    if (getpeername(fds[1], (struct sockaddr *) &peer, &addr_len) < 0) {
+        close_files(2, fds[0], fds[1]);
+        THROW_ERROR("failed to get the peer address");
+    }
+
+    // The reset of the connection is reported as HUP and ERR
+    struct linger linger = { .l_onoff = 1, .l_linger = 0 };
+    if (setsockopt(fds[0], SOL_SOCKET, SO_LINGER, &linger, sizeof(linger)) < 0) {
+        close_files(2, fds[0], fds[1]);
+        THROW_ERROR("failed to set SO_LINGER");
+    }
+    close(fds[0]);
+    struct pollfd pfd = { .fd = fds[1], .events = POLLIN | POLLOUT | POLLRDHUP };
+    if (poll(&pfd, 1, 1000) != 1 || !(pfd.revents & POLLHUP) || !(pfd.revents & POLLERR)) {
+        close(fds[1]);
+        THROW_ERROR("HUP and ERR are expected after the connection is reset");
+    }
+
+    // A closed connection has no peer
+    addr_len = sizeof(peer);
+    if (getpeername(fds[1], (struct sockaddr *) &peer, &addr_len) != -1 || errno != ENOTCONN) {
+        close(fds[1]);
+        THROW_ERROR("the peer address should be gone after the connection is reset");
+    }
+
+    close(fds[1]);
+    return 0;
+}
+
+int test_ip_pktinfo() {
+    // The receiving socket is bound to the wildcard address
+    int socks[2];
+    struct sockaddr_in servaddr;
+    if (udp_socket_pair(htonl(INADDR_ANY), socks, &servaddr) < 0) {
+        THROW_ERROR("failed to create the UDP sockets");
+    }
+    int recv_sock = socks[0];
+    int send_sock = socks[1];
+    int enable = 1;
+    if (setsockopt(recv_sock, IPPROTO_IP, IP_PKTINFO, &enable, sizeof(enable)) < 0) {
+        close_files(2, recv_sock, send_sock);
+        THROW_ERROR("failed to enable IP_PKTINFO");
+    }
+
+    if (sendto(send_sock, DEFAULT_MSG, sizeof(DEFAULT_MSG), 0,
+               (struct sockaddr *) &servaddr, sizeof(servaddr)) != sizeof(DEFAULT_MSG)) {
+        close_files(2, recv_sock, send_sock);
+        THROW_ERROR("sendto failed");
+    }
+
+    // The destination address of the datagram is received as a control message
+    char buf[32];
+    char control[CMSG_SPACE(sizeof(struct in_pktinfo))];
+    struct sockaddr_in client_addr;
+    struct iovec iov = { .iov_base = buf, .iov_len = sizeof(buf) };
+    struct msghdr msg = {
+        .msg_name = &client_addr,
+        .msg_namelen = sizeof(client_addr),
+        .msg_iov = &iov,
+        .msg_iovlen = 1,
+        .msg_control = control,
+        .msg_controllen = sizeof(control),
+    };
+    if (recvmsg(recv_sock, &msg, 0) != sizeof(DEFAULT_MSG)) {
+        close_files(2, recv_sock, send_sock);
+        THROW_ERROR("recvmsg failed");
+    }
+    struct cmsghdr *cmsg = CMSG_FIRSTHDR(&msg);
+    if (cmsg == NULL || cmsg->cmsg_level != IPPROTO_IP || cmsg->cmsg_type != IP_PKTINFO) {
+        close_files(2, recv_sock, send_sock);
+        THROW_ERROR("the IP_PKTINFO control message is expected");
+    }
+    struct in_pktinfo pktinfo;
+    memcpy(&pktinfo, CMSG_DATA(cmsg), sizeof(pktinfo));
+    if (pktinfo.ipi_addr.s_addr != htonl(INADDR_LOOPBACK)) {
+        close_files(2, recv_sock, send_sock);
+        THROW_ERROR("the destination address is wrong");
+    }
+
+    // The reply is sent from the source address given by the same control message
+    memset(&pktinfo, 0, sizeof(pktinfo));
+    pktinfo.ipi_spec_dst.s_addr = htonl(INADDR_LOOPBACK);
+    memcpy(CMSG_DATA(cmsg), &pktinfo, sizeof(pktinfo));
+    iov.iov_len = sizeof(DEFAULT_MSG);
+    msg.msg_controllen = CMSG_SPACE(sizeof(pktinfo));
+    if (sendmsg(recv_sock, &msg, 0) != sizeof(DEFAULT_MSG)) {
+        close_files(2, recv_sock, send_sock);
+        THROW_ERROR("sendmsg with IP_PKTINFO failed");
+    }
+    struct sockaddr_in reply_addr;
+    socklen_t addr_len = sizeof(reply_addr);
+    if (recvfrom(send_sock, buf, sizeof(buf), 0, (struct sockaddr *) &reply_addr,
+                 &addr_len) != sizeof(DEFAULT_MSG) ||
+            reply_addr.sin_addr.s_addr != htonl(INADDR_LOOPBACK) ||
+            reply_addr.sin_port != servaddr.sin_port) {
+        close_files(2, recv_sock, send_sock);
+        THROW_ERROR("the reply is not sent from the destination address");
+    }
+
+    close_files(2, recv_sock, send_sock);
+    return 0;
+}
+
+int test_so_rxq_ovfl() {
+    int socks[2];
+    struct sockaddr_in servaddr;
+    if (udp_socket_pair(htonl(INADDR_LOOPBACK), socks, &servaddr) < 0) {
+        THROW_ERROR("failed to create the UDP sockets");
+    }
+    int recv_sock = socks[0];
+    int send_sock = socks[1];
+    int enable = 1;
+    int rcvbuf = 4096;
+    if (setsockopt(recv_sock, SOL_SOCKET, SO_RCVBUF, &rcvbuf, sizeof(rcvbuf)) < 0 ||
+            setsockopt(recv_sock, SOL_SOCKET, SO_RXQ_OVFL, &enable, sizeof(enable)) < 0) {
+        close_files(2, recv_sock, send_sock);
+        THROW_ERROR("failed to enable SO_RXQ_OVFL");
+    }
+
+    // Overflow the receive buffer, in which case the newest datagrams are dropped
+    char buf[1024] = {0};
+    for (int i = 0; i < 64; i++) {
+        if (sendto(send_sock, buf, sizeof(buf), 0, (struct sockaddr *) &servaddr,
+                   sizeof(servaddr)) != sizeof(buf)) {
+            close_files(2, recv_sock, send_sock);
+            THROW_ERROR("sendto failed");
+        }
+    }
+    while (recv(recv_sock, buf, sizeof(buf), MSG_DONTWAIT) > 0) {
+    }
+    if (errno != EAGAIN) {
+        close_files(2, recv_sock, send_sock);
+        THROW_ERROR("failed to drain the receive buffer");
+    }
+
+    // The next datagram received carries the number of the dropped datagrams
+    if (sendto(send_sock, buf, sizeof(buf), 0, (struct sockaddr *) &servaddr,
+               sizeof(servaddr)) != sizeof(buf)) {
+        close_files(2, recv_sock, send_sock);
+        THROW_ERROR("sendto failed");
+    }
+    char control[CMSG_SPACE(sizeof(uint32_t))];
+    struct iovec iov = { .iov_base = buf, .iov_len = sizeof(buf) };
+    struct msghdr msg = {
+        .msg_iov = &iov,
+        .msg_iovlen = 1,
+        .msg_control = control,
+        .msg_controllen = sizeof(control),
+    };
+    if (recvmsg(recv_sock, &msg, 0) != sizeof(buf)) {
+        close_files(2, recv_sock, send_sock);
+        THROW_ERROR("recvmsg failed");
+    }
+    struct cmsghdr *cmsg = CMSG_FIRSTHDR(&msg);
+    if (cmsg == NULL || cmsg->cmsg_level != SOL_SOCKET || cmsg->cmsg_type != SO_RXQ_OVFL) {
+        close_files(2, recv_sock, send_sock);
+        THROW_ERROR("the SO_RXQ_OVFL control message is expected");
+    }
+    uint32_t drop_count;
+    memcpy(&drop_count, CMSG_DATA(cmsg), sizeof(drop_count));
+    if (drop_count == 0) {
+        close_files(2, recv_sock, send_sock);
+        THROW_ERROR("the dropped datagrams should be counted");
+    }
+
+    close_files(2, recv_sock, send_sock);
+    return 0;
+}
+
+int test_udp_implicit_bind() {
+    int socks[2];
+    struct sockaddr_in servaddr;
+    if (udp_socket_pair(htonl(INADDR_LOOPBACK), socks, &servaddr) < 0) {
+        THROW_ERROR("failed to create the UDP sockets");
+    }
+    int recv_sock = socks[0];
+    int send_sock = socks[1];
+
+    // The unbound socket has no port before sending
+    struct sockaddr_in addr;
+    socklen_t addr_len = sizeof(addr);
+    if (getsockname(send_sock, (struct sockaddr *) &addr, &addr_len) < 0 ||
+            addr.sin_port != 0) {
+        close_files(2, recv_sock, send_sock);
+        THROW_ERROR("the unbound socket should have no port");
+    }
+
+    // The first send binds the socket to an ephemeral port, which stays the same
+    in_port_t port = 0;
+    for (int i = 0; i < 3; i++) {
+        if (sendto(send_sock, DEFAULT_MSG, sizeof(DEFAULT_MSG), 0,
+                   (struct sockaddr *) &servaddr, sizeof(servaddr)) != sizeof(DEFAULT_MSG)) {
+            close_files(2, recv_sock, send_sock);
+            THROW_ERROR("sendto failed");
+        }
+        addr_len = sizeof(addr);
+        if (getsockname(send_sock, (struct sockaddr *) &addr, &addr_len) < 0 ||
+                addr.sin_port == 0 || (port != 0 && addr.sin_port != port)) {
+            close_files(2, recv_sock, send_sock);
+            THROW_ERROR("the socket should be bound to a stable port after sending");
+        }
+        port = addr.sin_port;
+    }
+
+    // The datagrams come from the bound port
+    char buf[32];
+    struct sockaddr_in src_addr;
+    addr_len = sizeof(src_addr);
+    if (recvfrom(recv_sock, buf, sizeof(buf), 0, (struct sockaddr *) &src_addr,
+                 &addr_len) != sizeof(DEFAULT_MSG) || src_addr.sin_port != port) {
+        close_files(2, recv_sock, send_sock);
+        THROW_ERROR("the datagram should come from the bound port");
+    }
+
+    close_files(2, recv_sock, send_sock);
+    return 0;
+}
+
+int test_poll_err_on_connect_failure() {
+    int sock = socket(AF_INET, SOCK_STREAM | SOCK_NONBLOCK, 0);
+    if (sock < 0) {
+        THROW_ERROR("create socket error");
+    }
+
+    // No one is listening on this port
+    struct sockaddr_in servaddr;
+    memset(&servaddr, 0, sizeof(servaddr));
+    servaddr.sin_family = AF_INET;
+    servaddr.sin_addr.s_addr = htonl(INADDR_LOOPBACK);
+    servaddr.sin_port = htons(8833);
+    // The connection of a nonblocking TCP socket always completes later
+    if (connect(sock, (struct sockaddr *) &servaddr, sizeof(servaddr)) != -1 ||
+            errno != EINPROGRESS) {
+        close(sock);
+        THROW_ERROR("nonblocking connect should be in progress");
+    }
+
+    // POLLERR is reported even if not requested
+    struct pollfd pfd = { .fd = sock, .events = 0 };
+    if (poll(&pfd, 1, 1000) != 1 || !(pfd.revents & POLLERR)) {
+        close(sock);
+        THROW_ERROR("poll should report POLLERR for the failed connect");
+    }
+
+    // Like Linux, select reports an error as readable and writable
+    fd_set readfds, writefds, exceptfds;
+    FD_ZERO(&readfds);
+    FD_ZERO(&writefds);
+    FD_ZERO(&exceptfds);
+    FD_SET(sock, &readfds);
+    FD_SET(sock, &writefds);
+    FD_SET(sock, &exceptfds);
+    struct timeval timeout = { .tv_sec = 0, .tv_usec = 0 };
+    if (select(sock + 1, &readfds, &writefds, &exceptfds, &timeout) != 2 ||
+            !FD_ISSET(sock, &readfds) || !FD_ISSET(sock, &writefds)) {
+        close(sock);
+        THROW_ERROR("select should report the failed connect");
+    }
+
+    int error = 0;
+    socklen_t optlen = sizeof(error);
+    if (getsockopt(sock, SOL_SOCKET, SO_ERROR, &error, &optlen) < 0 ||
+            error != ECONNREFUSED) {
+        close(sock);
+        THROW_ERROR("SO_ERROR should be ECONNREFUSED");
+    }
+
+    close(sock);
+    return 0;
+}
+
+int test_recv_trunc() {
+    int socks[2];
+    struct sockaddr_in servaddr;
+    if (udp_socket_pair(htonl(INADDR_LOOPBACK), socks, &servaddr) < 0) {
+        THROW_ERROR("failed to create the UDP sockets");
+    }
+    int recv_sock = socks[0];
+    int send_sock = socks[1];
+    if (sendto(send_sock, DEFAULT_MSG, sizeof(DEFAULT_MSG), 0,
+               (struct sockaddr *) &servaddr, sizeof(servaddr)) != sizeof(DEFAULT_MSG)) {
+        close_files(2, recv_sock, send_sock);
+        THROW_ERROR("sendto failed");
+    }
+
+    // The real length of the datagram is returned, while only the part that
+    // fits in the buffer is copied
+    char buf[8] = {0};
+    if (recv(recv_sock, buf, sizeof(buf), MSG_TRUNC) != sizeof(DEFAULT_MSG) ||
+            strncmp(buf, DEFAULT_MSG, sizeof(buf)) != 0) {
+        close_files(2, recv_sock, send_sock);
+        THROW_ERROR("recv with MSG_TRUNC should return the real length");
+    }
+    // The rest of the datagram is discarded
+    if (recv(recv_sock, buf, sizeof(buf), MSG_DONTWAIT) != -1 || errno != EAGAIN) {
+        close_files(2, recv_sock, send_sock);
+        THROW_ERROR("the truncated datagram should be consumed");
+    }
+
+    close_files(2, recv_sock, send_sock);
+    return 0;
+}
+
+int test_unknown_sockopt_pass_through() {
+    int sock = socket(AF_INET, SOCK_STREAM, 0);
+    if (sock < 0) {
+        THROW_ERROR("create socket error");
+    }
+
+    // The options not known by the LibOS are forwarded to the host
+    int keepalive = 1, keepidle = 30;
+    if (setsockopt(sock, SOL_SOCKET, SO_KEEPALIVE, &keepalive, sizeof(keepalive)) < 0 ||
+            setsockopt(sock, IPPROTO_TCP, TCP_KEEPIDLE, &keepidle, sizeof(keepidle)) < 0) {
+        close(sock);
+        THROW_ERROR("failed to set the options");
+    }
+
+    int actual = 0;
+    socklen_t optlen = sizeof(actual);
+    if (getsockopt(sock, SOL_SOCKET, SO_KEEPALIVE, &actual, &optlen) < 0 ||
+            optlen != sizeof(actual) || actual != keepalive) {
+        close(sock);
+        THROW_ERROR("SO_KEEPALIVE value mismatch");
+    }
+    optlen = sizeof(actual);
+    if (getsockopt(sock, IPPROTO_TCP, TCP_KEEPIDLE, &actual, &optlen) < 0 ||
+            optlen != sizeof(actual) || actual != keepidle) {
+        close(sock);
+        THROW_ERROR("TCP_KEEPIDLE value mismatch");
+    }
+
+    // The errors of the host are returned as is
+    if (setsockopt(sock, SOL_SOCKET, 0x7fff, &keepalive, sizeof(keepalive)) != -1 ||
+            errno != ENOPROTOOPT) {
+        close(sock);
+        THROW_ERROR("an option unknown to the host should fail with ENOPROTOOPT");
+    }
+
+    close(sock);
+    return 0;
+}
+
+#define NUM_REUSEPORT_LISTENERS 2
+#define NUM_REUSEPORT_CONNECTIONS 32
+
+int test_reuseport_accept_balance() {
+    int listen_fds[NUM_REUSEPORT_LISTENERS];
+    int client_fds[NUM_REUSEPORT_CONNECTIONS];
+    int accepted_counts[NUM_REUSEPORT_LISTENERS] = {0};
+    int num_listeners = 0, num_clients = 0, ret = -1;
+
+    struct sockaddr_in servaddr;
+    memset(&servaddr, 0, sizeof(servaddr));
+    servaddr.sin_family = AF_INET;
+    servaddr.sin_addr.s_addr = htonl(INADDR_LOOPBACK);
+    servaddr.sin_port = htons(8837);
+
+    int enable = 1;
+    for (; num_listeners < NUM_REUSEPORT_LISTENERS; num_listeners++) {
+        int fd = socket(AF_INET, SOCK_STREAM | SOCK_NONBLOCK, 0);
+        if (fd < 0) {
+            printf("create socket error\n");
+            goto out;
+        }
+        listen_fds[num_listeners] = fd;
+        if (setsockopt(fd, SOL_SOCKET, SO_REUSEPORT, &enable, sizeof(enable)) < 0 ||
+                bind(fd, (struct sockaddr *) &servaddr, sizeof(servaddr)) < 0 ||
+                listen(fd, NUM_REUSEPORT_CONNECTIONS) < 0) {
+            num_listeners++;
+            printf("failed to listen with SO_REUSEPORT\n");
+            goto out;
+        }
+    }
+
+    for (; num_clients < NUM_REUSEPORT_CONNECTIONS; num_clients++) {
+        int fd = socket(AF_INET, SOCK_STREAM, 0);
+        if (fd < 0) {
+            printf("create socket error\n");
+            goto out;
+        }
+        client_fds[num_clients] = fd;
+        if (connect(fd, (struct sockaddr *) &servaddr, sizeof(servaddr)) < 0) {
+            num_clients++;
+            printf("failed to connect\n");
+            goto out;
+        }
+    }
+
+    // The host spreads the connections among the listeners by the hash of
+    // the addresses, so each listener gets about the same number of them
+    int total = 0;
+    for (int i = 0; i < NUM_REUSEPORT_LISTENERS; i++) {
+        int fd;
+        while ((fd = accept(listen_fds[i], NULL, NULL)) >= 0) {
+            accepted_counts[i]++;
+            close(fd);
+        }
+        if (errno != EAGAIN) {
+            printf("failed to accept\n");
+            goto out;
+        }
+        total += accepted_counts[i];
+    }
+    if (total != NUM_REUSEPORT_CONNECTIONS) {
+        printf("only %d connections are accepted\n", total);
+        goto out;
+    }
+    for (int i = 0; i < NUM_REUSEPORT_LISTENERS; i++) {
+        if (accepted_counts[i] < NUM_REUSEPORT_CONNECTIONS / 8 ||
+                accepted_counts[i] > NUM_REUSEPORT_CONNECTIONS * 7 / 8) {
+            printf("listener %d accepts %d of %d connections\n", i, accepted_counts[i],
+                   NUM_REUSEPORT_CONNECTIONS);
+            goto out;
+        }
+    }
+    ret = 0;
+
+out:
+    for (int i = 0; i < num_clients; i++) {
+        close(client_fds[i]);
+    }
+    for (int i = 0; i < num_listeners; i++) {
+        close(listen_fds[i]);
+    }
+    if (ret < 0) {
+        THROW_ERROR("connections are not balanced among SO_REUSEPORT listeners");
+    }
+    return 0;
+}
+
+int test_connect_bound_datagram_socket() {
+    int udp_fd = socket(AF_INET, SOCK_DGRAM, 0);
+    if (udp_fd < 0) {
+        THROW_ERROR("failed to create a UDP socket");
+    }
+
+    struct sockaddr_in addr;
+    memset(&addr, 0, sizeof(addr));
+    addr.sin_family = AF_INET;
+    addr.sin_addr.s_addr = htonl(INADDR_ANY);
+    addr.sin_port = htons(8838);
+    if (bind(udp_fd, (struct sockaddr *) &addr, sizeof(addr)) < 0) {
+        close(udp_fd);
+        THROW_ERROR("failed to bind");
+    }
+
+    // Connecting a bound socket picks the local IP address by the route, but
+    // keeps the bound port
+    struct sockaddr_in peer;
+    memset(&peer, 0, sizeof(peer));
+    peer.sin_family = AF_INET;
+    peer.sin_addr.s_addr = htonl(INADDR_LOOPBACK);
+    peer.sin_port = htons(8839);
+    if (connect(udp_fd, (struct sockaddr *) &peer, sizeof(peer)) < 0) {
+        close(udp_fd);
+        THROW_ERROR("failed to connect");
+    }
+    socklen_t addr_len = sizeof(addr);
+    if (getsockname(udp_fd, (struct sockaddr *) &addr, &addr_len) < 0) {
+        close(udp_fd);
+        THROW_ERROR("failed to get the local address");
+    }
+    if (addr.sin_port != htons(8838) || addr.sin_addr.s_addr != htonl(INADDR_LOOPBACK)) {
+        close(udp_fd);
+        THROW_ERROR("connecting should keep the bound port");
+    }
+
+    // So does connecting to another peer
+    peer.sin_port = htons(8840);
+    if (connect(udp_fd, (struct sockaddr *) &peer, sizeof(peer)) < 0) {
+        close(udp_fd);
+        THROW_ERROR("failed to connect again");
+    }
+    addr_len = sizeof(addr);
+    if (getsockname(udp_fd, (struct sockaddr *) &addr, &addr_len) < 0 ||
+            addr.sin_port != htons(8838)) {
+        close(udp_fd);
+        THROW_ERROR("connecting again should keep the bound port");
+    }
+
+    close(udp_fd);
+    return 0;
+}
+
+static long elapsed_ms(const struct timespec *start) {
+    struct timespec now;
+    clock_gettime(CLOCK_MONOTONIC, &now);
+    return (now.tv_sec - start->tv_sec) * 1000 + (now.tv_nsec - start->tv_nsec) / 1000000;
+}
+
+// Returns the errno of a recv that finds no data, or 0 if it receives data
+static int recv_errno(int fd, long *elapsed) {
+    char buf[16];
+    struct timespec start;
+    clock_gettime(CLOCK_MONOTONIC, &start);
+    ssize_t ret = recv(fd, buf, sizeof(buf), 0);
+    *elapsed = elapsed_ms(&start);
+    return ret < 0 ? errno : 0;
+}
+
+static int set_nonblocking_by_fcntl(int fd, int nonblocking) {
+    int flags = fcntl(fd, F_GETFL, 0);
+    if (flags < 0) {
+        return -1;
+    }
+    flags = nonblocking ? (flags | O_NONBLOCK) : (flags & ~O_NONBLOCK);
+    return fcntl(fd, F_SETFL, flags);
+}
+
+static int set_nonblocking_by_ioctl(int fd, int nonblocking) {
+    return ioctl(fd, FIONBIO, &nonblocking);
+}
+
+int test_toggle_nonblocking_at_runtime() {
+    static const struct {
+        const char *name;
+        int (*set_nonblocking)(int fd, int nonblocking);
+    } setters[] = {
+        { "fcntl", set_nonblocking_by_fcntl },
+        { "FIONBIO", set_nonblocking_by_ioctl },
+    };
+
+    int recv_fd = socket(AF_INET, SOCK_DGRAM, 0);
+    int send_fd = socket(AF_INET, SOCK_DGRAM, 0);
+    if (recv_fd < 0 || send_fd < 0) {
+        close_files(2, recv_fd, send_fd);
+        THROW_ERROR("failed to create UDP sockets");
+    }
+    struct sockaddr_in addr;
+    socklen_t addr_len = sizeof(addr);
+    memset(&addr, 0, sizeof(addr));
+    addr.sin_family = AF_INET;
+    addr.sin_addr.s_addr = htonl(INADDR_LOOPBACK);
+    if (bind(recv_fd, (struct sockaddr *) &addr, sizeof(addr)) < 0 ||
+            getsockname(recv_fd, (struct sockaddr *) &addr, &addr_len) < 0) {
+        close_files(2, recv_fd, send_fd);
+        THROW_ERROR("failed to bind");
+    }
+
+    // A blocking recv waits for the receive timeout before failing
+    struct timeval timeout = { .tv_sec = 0, .tv_usec = 200 * 1000 };
+    if (setsockopt(recv_fd, SOL_SOCKET, SO_RCVTIMEO, &timeout, sizeof(timeout)) < 0) {
+        close_files(2, recv_fd, send_fd);
+        THROW_ERROR("failed to set SO_RCVTIMEO");
+    }
+
+    for (int si = 0; si < ARRAY_SIZE(setters); si++) {
+        long elapsed;
+        const char *name = setters[si].name;
+        if (setters[si].set_nonblocking(recv_fd, 1) < 0 ||
+                !(fcntl(recv_fd, F_GETFL, 0) & O_NONBLOCK)) {
+            close_files(2, recv_fd, send_fd);
+            THROW_ERROR("failed to set the nonblocking mode by %s", name);
+        }
+        if (recv_errno(recv_fd, &elapsed) != EAGAIN || elapsed >= 100) {
+            close_files(2, recv_fd, send_fd);
+            THROW_ERROR("recv should fail immediately after setting %s", name);
+        }
+
+        if (setters[si].set_nonblocking(recv_fd, 0) < 0 ||
+                (fcntl(recv_fd, F_GETFL, 0) & O_NONBLOCK)) {
+            close_files(2, recv_fd, send_fd);
+            THROW_ERROR("failed to clear the nonblocking mode by %s", name);
+        }
+        if (recv_errno(recv_fd, &elapsed) != EAGAIN || elapsed < 100) {
+            close_files(2, recv_fd, send_fd);
+            THROW_ERROR("recv should block after clearing %s", name);
+        }
+
+        // The data sent before a blocking recv is received at once
+        if (sendto(send_fd, "hello", 5, 0, (struct sockaddr *) &addr, sizeof(addr)) != 5 ||
+                recv_errno(recv_fd, &elapsed) != 0) {
+            close_files(2, recv_fd, send_fd);
+            THROW_ERROR("blocking recv should receive the data after clearing %s", name);
+        }
+    }
+
+    close_files(2, recv_fd, send_fd);
+    return 0;
+}
+
 int test_poll() {
     int child_pid = 0;
     int client_fd = connect_with_child(8805, &child_pid);
@@ -511,6 +2464,40 @@ static test_case_t test_cases[] = {
     TEST_CASE(test_fcntl_setfl_and_getfl),
     TEST_CASE(test_poll),
     TEST_CASE(test_poll_events_unchanged),
+    TEST_CASE(test_sockopt_reuseaddr),
+    TEST_CASE(test_nonblocking_connect_refused),
+    TEST_CASE(test_udp_shutdown_write),
+    TEST_CASE(test_recvmmsg),
+    TEST_CASE(test_sendmmsg_connectionless),
+    TEST_CASE(test_ioctl_fionbio),
+    TEST_CASE(test_so_linger_abortive_close),
+    TEST_CASE(test_oob_data),
+    TEST_CASE(test_oob_inline),
+    TEST_CASE(test_getsockname_getpeername),
+    TEST_CASE(test_udp_disconnect),
+    TEST_CASE(test_so_broadcast),
+    TEST_CASE(test_ip_multicast),
+    TEST_CASE(test_tcp_cork_and_msg_more),
+    TEST_CASE(test_so_bindtodevice),
+    TEST_CASE(test_dup_socket),
+    TEST_CASE(test_tcp_info),
+    TEST_CASE(test_ioctl_queue_len),
+    TEST_CASE(test_socket_info),
+    TEST_CASE(test_ip_tos_and_ttl),
+    TEST_CASE(test_stream_pair_checks),
+    TEST_CASE(test_so_reuseport),
+    TEST_CASE(test_accept_peer_addr),
+    TEST_CASE(test_sendfile_to_socket),
+    TEST_CASE(test_poll_hup_on_reset),
+    TEST_CASE(test_ip_pktinfo),
+    TEST_CASE(test_so_rxq_ovfl),
+    TEST_CASE(test_udp_implicit_bind),
+    TEST_CASE(test_poll_err_on_connect_failure),
+    TEST_CASE(test_recv_trunc),
+    TEST_CASE(test_unknown_sockopt_pass_through),
+    TEST_CASE(test_reuseport_accept_balance),
+    TEST_CASE(test_connect_bound_datagram_socket),
+    TEST_CASE(test_toggle_nonblocking_at_runtime),
     TEST_CASE(test_exit_group),
 };
 
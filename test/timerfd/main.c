@@ -1,9 +1,15 @@
+#include <stdint.h>
 #include <stdio.h>
 #include <sys/timerfd.h>
+#include <sys/ioctl.h>
 #include <sys/select.h>
 #include <time.h>
 #include "test.h"
 
+#ifndef TFD_IOC_SET_TICKS
+#define TFD_IOC_SET_TICKS _IOW('T', 0, uint64_t)
+#endif
+
 // ============================================================================
 // Test cases for timerfd full process
 // ============================================================================
@@ -80,6 +86,59 @@ int test_invalid_argument() {
     return 0;
 }
 
+int test_ioctl_fionbio() {
+    int tfd = timerfd_create(CLOCK_REALTIME,  0);
+    if (tfd <= 0) {
+        THROW_ERROR("timerfd_create(CLOCK_REALTIME, ...) failed");
+    }
+
+    int nonblocking = 1;
+    if (ioctl(tfd, FIONBIO, &nonblocking) < 0) {
+        close(tfd);
+        THROW_ERROR("ioctl FIONBIO failed");
+    }
+
+    // The timer is not armed, so the read fails instead of blocking
+    char dummybuf[8];
+    if (read(tfd, dummybuf, sizeof(dummybuf)) >= 0 || errno != EAGAIN) {
+        close(tfd);
+        THROW_ERROR("read should fail with EAGAIN");
+    }
+
+    close(tfd);
+    return 0;
+}
+
+int test_ioctl_set_ticks() {
+    int tfd = timerfd_create(CLOCK_MONOTONIC, TFD_NONBLOCK);
+    if (tfd <= 0) {
+        THROW_ERROR("timerfd_create(CLOCK_MONOTONIC, ...) failed");
+    }
+
+    uint64_t ticks = 3;
+    if (ioctl(tfd, TFD_IOC_SET_TICKS, &ticks) < 0) {
+        close(tfd);
+        THROW_ERROR("ioctl TFD_IOC_SET_TICKS failed");
+    }
+
+    uint64_t expirations = 0;
+    if (read(tfd, &expirations, sizeof(expirations)) != sizeof(expirations) ||
+            expirations != ticks) {
+        close(tfd);
+        THROW_ERROR("failed to read the ticks set by ioctl");
+    }
+
+    // Other ioctls are not supported by timerfd
+    int nbytes;
+    if (ioctl(tfd, FIONREAD, &nbytes) >= 0 || errno != ENOTTY) {
+        close(tfd);
+        THROW_ERROR("ioctl FIONREAD should fail with ENOTTY");
+    }
+
+    close(tfd);
+    return 0;
+}
+
 // ============================================================================
 // Test suite
 // ============================================================================
@@ -87,6 +146,8 @@ int test_invalid_argument() {
 static test_case_t test_cases[] = {
     TEST_CASE(test_timerfd),
     TEST_CASE(test_invalid_argument),
+    TEST_CASE(test_ioctl_fionbio),
+    TEST_CASE(test_ioctl_set_ticks),
 };
 
 int main() {
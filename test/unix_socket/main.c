@@ -1,18 +1,25 @@
+#define _GNU_SOURCE
 #include <sys/syscall.h>
 #include <sys/wait.h>
 #include <sys/socket.h>
 #include <sys/un.h>
 #include <sys/ioctl.h>
+#include <sys/resource.h>
+#include <linux/sockios.h>
+#include <fcntl.h>
 #include <poll.h>
 #include <unistd.h>
 #include <stdlib.h>
 #include <stdio.h>
 #include <spawn.h>
 #include <string.h>
+#include <time.h>
 #include <sys/epoll.h>
 #include <pthread.h>
+#include <signal.h>
 
 #include "test.h"
+#include "test_socket.h"
 
 #define ECHO_MSG "echo msg for unix_socket test"
 
@@ -558,6 +565,1247 @@ int test_sendmsg_recvmsg() {
     return ret;
 }
 
+int test_recv_peek() {
+    int socks[2];
+    char header[4] = {0};
+    char buf[64] = {0};
+    if (socketpair(AF_UNIX, SOCK_STREAM, 0, socks) < 0) {
+        THROW_ERROR("socketpair failed");
+    }
+
+    if (write(socks[0], ECHO_MSG, sizeof(ECHO_MSG)) != sizeof(ECHO_MSG)) {
+        THROW_ERROR("failed to write to socket");
+    }
+
+    if (recv(socks[1], header, sizeof(header), MSG_PEEK) != sizeof(header)) {
+        THROW_ERROR("failed to peek the header");
+    }
+
+    if (recv(socks[1], buf, sizeof(buf), 0) != sizeof(ECHO_MSG)) {
+        THROW_ERROR("failed to read the full message");
+    }
+
+    // The peeked bytes must be returned again by the following read
+    if (strncmp(header, ECHO_MSG, sizeof(header)) != 0 ||
+            strncmp(buf, ECHO_MSG, sizeof(ECHO_MSG)) != 0) {
+        THROW_ERROR("peeked data mismatch");
+    }
+
+    close_files(2, socks[0], socks[1]);
+    return 0;
+}
+
+int test_scm_rights() {
+    int socks[2], pipe_fds[2];
+    char data = 'x';
+    char buf[32] = {0};
+    if (socketpair(AF_UNIX, SOCK_STREAM, 0, socks) < 0) {
+        THROW_ERROR("socketpair failed");
+    }
+    if (pipe(pipe_fds) < 0) {
+        THROW_ERROR("pipe failed");
+    }
+
+    // Send the write end of the pipe
+    char send_control[CMSG_SPACE(sizeof(int))] = {0};
+    struct iovec send_iov = {.iov_base = &data, .iov_len = 1};
+    struct msghdr send_msg = {
+        .msg_iov = &send_iov,
+        .msg_iovlen = 1,
+        .msg_control = send_control,
+        .msg_controllen = sizeof(send_control),
+    };
+    struct cmsghdr *cmsg = CMSG_FIRSTHDR(&send_msg);
+    cmsg->cmsg_level = SOL_SOCKET;
+    cmsg->cmsg_type = SCM_RIGHTS;
+    cmsg->cmsg_len = CMSG_LEN(sizeof(int));
+    memcpy(CMSG_DATA(cmsg), &pipe_fds[1], sizeof(int));
+    if (sendmsg(socks[0], &send_msg, 0) != 1) {
+        THROW_ERROR("sendmsg failed");
+    }
+    // The received fd must remain valid after the sender closes it
+    close(pipe_fds[1]);
+
+    char recv_control[CMSG_SPACE(sizeof(int))] = {0};
+    struct iovec recv_iov = {.iov_base = buf, .iov_len = sizeof(buf)};
+    struct msghdr recv_msg = {
+        .msg_iov = &recv_iov,
+        .msg_iovlen = 1,
+        .msg_control = recv_control,
+        .msg_controllen = sizeof(recv_control),
+    };
+    if (recvmsg(socks[1], &recv_msg, 0) != 1) {
+        THROW_ERROR("recvmsg failed");
+    }
+    if (recv_msg.msg_flags & MSG_CTRUNC) {
+        THROW_ERROR("control message should not be truncated");
+    }
+
+    cmsg = CMSG_FIRSTHDR(&recv_msg);
+    if (cmsg == NULL || cmsg->cmsg_level != SOL_SOCKET || cmsg->cmsg_type != SCM_RIGHTS) {
+        THROW_ERROR("no SCM_RIGHTS control message received");
+    }
+    int recv_fd;
+    memcpy(&recv_fd, CMSG_DATA(cmsg), sizeof(int));
+
+    if (write(recv_fd, ECHO_MSG, sizeof(ECHO_MSG)) != sizeof(ECHO_MSG)) {
+        THROW_ERROR("failed to write to the received fd");
+    }
+    if (read(pipe_fds[0], buf, sizeof(buf)) != sizeof(ECHO_MSG) ||
+            strncmp(buf, ECHO_MSG, sizeof(ECHO_MSG)) != 0) {
+        THROW_ERROR("data read from pipe mismatch");
+    }
+
+    close_files(4, socks[0], socks[1], pipe_fds[0], recv_fd);
+    return 0;
+}
+
+int test_peercred() {
+    int socks[2];
+    struct ucred cred;
+    socklen_t len = sizeof(cred);
+    if (socketpair(AF_UNIX, SOCK_STREAM, 0, socks) < 0) {
+        THROW_ERROR("socketpair failed");
+    }
+
+    if (getsockopt(socks[0], SOL_SOCKET, SO_PEERCRED, &cred, &len) < 0) {
+        THROW_ERROR("getsockopt SO_PEERCRED failed");
+    }
+    // Both ends of a socket pair are owned by the creating process
+    if (len != sizeof(cred) || cred.pid != getpid() || cred.uid != getuid() ||
+            cred.gid != getgid()) {
+        THROW_ERROR("peer credentials mismatch");
+    }
+
+    close_files(2, socks[0], socks[1]);
+    return 0;
+}
+
+int test_epoll_rdhup() {
+    int socks[2];
+    struct epoll_event event;
+    struct epoll_event polled_events;
+    if (socketpair(AF_UNIX, SOCK_STREAM, 0, socks) < 0) {
+        THROW_ERROR("socketpair failed");
+    }
+
+    int ep_fd = epoll_create1(0);
+    if (ep_fd < 0) {
+        THROW_ERROR("failed to create an epoll");
+    }
+
+    event.events = EPOLLIN | EPOLLRDHUP | EPOLLET;
+    event.data.u32 = socks[1];
+    if (epoll_ctl(ep_fd, EPOLL_CTL_ADD, socks[1], &event) < 0) {
+        THROW_ERROR("failed to do epoll ctl");
+    }
+
+    // The peer half-closes the stream
+    if (shutdown(socks[0], SHUT_WR) < 0) {
+        THROW_ERROR("failed to shutdown");
+    }
+
+    if (epoll_wait(ep_fd, &polled_events, 1, 1000) != 1) {
+        THROW_ERROR("failed to do epoll wait");
+    }
+    if (!(polled_events.events & EPOLLRDHUP) || (polled_events.events & EPOLLHUP)) {
+        THROW_ERROR("only RDHUP is expected for a half-closed stream");
+    }
+
+    // Edge-triggered epoll should report the event exactly once
+    if (epoll_wait(ep_fd, &polled_events, 1, 0) != 0) {
+        THROW_ERROR("RDHUP is reported more than once");
+    }
+
+    close_files(3, socks[0], socks[1], ep_fd);
+    return 0;
+}
+
+int test_accept4_flags() {
+    char *sock_path = "/tmp/accept4.sock";
+    char buf[16];
+    struct sockaddr_un addr;
+    memset(&addr, 0, sizeof(struct sockaddr_un));
+    addr.sun_family = AF_UNIX;
+    strcpy(addr.sun_path, sock_path);
+    socklen_t addr_len = strlen(addr.sun_path) + sizeof(addr.sun_family) + 1;
+    unlink(addr.sun_path);
+
+    int listen_fd = socket(AF_UNIX, SOCK_STREAM, 0);
+    if (listen_fd == -1) {
+        THROW_ERROR("failed to create a unix socket");
+    }
+    if (bind(listen_fd, (struct sockaddr *)&addr, addr_len) == -1 ||
+            listen(listen_fd, 5) == -1) {
+        close(listen_fd);
+        THROW_ERROR("failed to listen");
+    }
+
+    int client_fd = socket(AF_UNIX, SOCK_STREAM, 0);
+    if (client_fd == -1 || connect(client_fd, (struct sockaddr *)&addr, addr_len) == -1) {
+        close(listen_fd);
+        THROW_ERROR("failed to connect");
+    }
+
+    int accepted_fd = accept4(listen_fd, NULL, NULL, SOCK_NONBLOCK | SOCK_CLOEXEC);
+    if (accepted_fd == -1) {
+        close_files(2, listen_fd, client_fd);
+        THROW_ERROR("failed to accept socket");
+    }
+
+    // Both flags must be applied to the accepted socket without calling fcntl
+    if (!(fcntl(accepted_fd, F_GETFL) & O_NONBLOCK) ||
+            !(fcntl(accepted_fd, F_GETFD) & FD_CLOEXEC)) {
+        close_files(3, listen_fd, client_fd, accepted_fd);
+        THROW_ERROR("flags of the accepted socket mismatch");
+    }
+    if (read(accepted_fd, buf, sizeof(buf)) != -1 || errno != EAGAIN) {
+        close_files(3, listen_fd, client_fd, accepted_fd);
+        THROW_ERROR("read on the accepted socket should not block");
+    }
+
+    close_files(3, listen_fd, client_fd, accepted_fd);
+    return 0;
+}
+
+int test_recv_timeout() {
+    int socks[2];
+    char buf[16];
+    struct timeval timeout = { .tv_sec = 0, .tv_usec = 100 * 1000 };
+    struct timeval actual = { 0 };
+    socklen_t optlen = sizeof(actual);
+    if (socketpair(AF_UNIX, SOCK_STREAM, 0, socks) < 0) {
+        THROW_ERROR("socketpair failed");
+    }
+
+    if (setsockopt(socks[1], SOL_SOCKET, SO_RCVTIMEO, &timeout, sizeof(timeout)) < 0) {
+        THROW_ERROR("setsockopt SO_RCVTIMEO failed");
+    }
+    if (getsockopt(socks[1], SOL_SOCKET, SO_RCVTIMEO, &actual, &optlen) < 0 ||
+            optlen != sizeof(actual) || actual.tv_sec != timeout.tv_sec ||
+            actual.tv_usec != timeout.tv_usec) {
+        THROW_ERROR("SO_RCVTIMEO value mismatch");
+    }
+
+    // No data is available, so the blocking read fails once the timeout is up
+    struct timespec start, end;
+    clock_gettime(CLOCK_MONOTONIC, &start);
+    if (read(socks[1], buf, sizeof(buf)) != -1 || errno != EAGAIN) {
+        THROW_ERROR("read should time out");
+    }
+    clock_gettime(CLOCK_MONOTONIC, &end);
+    long elapsed_ms = (end.tv_sec - start.tv_sec) * 1000 + (end.tv_nsec - start.tv_nsec) / 1000000;
+    if (elapsed_ms < 90 || elapsed_ms > 1000) {
+        THROW_ERROR("read should time out after about 100ms");
+    }
+
+    // Data available before the timeout is still returned
+    if (write(socks[0], ECHO_MSG, sizeof(ECHO_MSG)) != sizeof(ECHO_MSG)) {
+        THROW_ERROR("failed to write to socket");
+    }
+    if (read(socks[1], buf, sizeof(buf)) != sizeof(buf)) {
+        THROW_ERROR("failed to read before the timeout");
+    }
+
+    close_files(2, socks[0], socks[1]);
+    return 0;
+}
+
+int test_sndbuf() {
+    char *sock_path = "/tmp/sndbuf.sock";
+    char buf[64 * 1024] = {0};
+    struct sockaddr_un addr;
+    memset(&addr, 0, sizeof(struct sockaddr_un));
+    addr.sun_family = AF_UNIX;
+    strcpy(addr.sun_path, sock_path);
+    socklen_t addr_len = strlen(addr.sun_path) + sizeof(addr.sun_family) + 1;
+    unlink(addr.sun_path);
+
+    int listen_fd = socket(AF_UNIX, SOCK_STREAM, 0);
+    if (listen_fd == -1) {
+        THROW_ERROR("failed to create a unix socket");
+    }
+    if (bind(listen_fd, (struct sockaddr *)&addr, addr_len) == -1 ||
+            listen(listen_fd, 5) == -1) {
+        close(listen_fd);
+        THROW_ERROR("failed to listen");
+    }
+
+    int client_fd = socket(AF_UNIX, SOCK_STREAM | SOCK_NONBLOCK, 0);
+    if (client_fd == -1) {
+        close(listen_fd);
+        THROW_ERROR("failed to create a unix socket");
+    }
+
+    int size = -1;
+    if (setsockopt(client_fd, SOL_SOCKET, SO_SNDBUF, &size, sizeof(size)) != -1 ||
+            errno != EINVAL) {
+        close_files(2, listen_fd, client_fd);
+        THROW_ERROR("negative SO_SNDBUF should be rejected");
+    }
+
+    // Like Linux, the size is doubled
+    size = 8192;
+    int actual = 0;
+    socklen_t optlen = sizeof(actual);
+    if (setsockopt(client_fd, SOL_SOCKET, SO_SNDBUF, &size, sizeof(size)) < 0 ||
+            getsockopt(client_fd, SOL_SOCKET, SO_SNDBUF, &actual, &optlen) < 0 ||
+            actual != size * 2) {
+        close_files(2, listen_fd, client_fd);
+        THROW_ERROR("SO_SNDBUF value mismatch");
+    }
+
+    if (connect(client_fd, (struct sockaddr *)&addr, addr_len) == -1) {
+        close_files(2, listen_fd, client_fd);
+        THROW_ERROR("failed to connect");
+    }
+
+    // No more than the buffer size can be buffered
+    if (write(client_fd, buf, sizeof(buf)) != actual) {
+        close_files(2, listen_fd, client_fd);
+        THROW_ERROR("the buffered data should be limited by SO_SNDBUF");
+    }
+
+    close_files(2, listen_fd, client_fd);
+    return 0;
+}
+
+static void *write_second_chunk(void *arg) {
+    int fd = *(int *)arg;
+    // Make sure that the reader has received the first chunk and is blocked
+    usleep(100 * 1000);
+    if (write(fd, ECHO_MSG + 4, sizeof(ECHO_MSG) - 4) != sizeof(ECHO_MSG) - 4) {
+        return (void *) -1;
+    }
+    return NULL;
+}
+
+int test_recv_waitall() {
+    int socks[2];
+    char buf[sizeof(ECHO_MSG)] = {0};
+    pthread_t writer;
+    if (socketpair(AF_UNIX, SOCK_STREAM, 0, socks) < 0) {
+        THROW_ERROR("socketpair failed");
+    }
+
+    if (write(socks[0], ECHO_MSG, 4) != 4) {
+        close_files(2, socks[0], socks[1]);
+        THROW_ERROR("failed to write the first chunk");
+    }
+    if (pthread_create(&writer, NULL, write_second_chunk, &socks[0]) != 0) {
+        close_files(2, socks[0], socks[1]);
+        THROW_ERROR("failed to create a thread");
+    }
+
+    // The two chunks are received by a single recv
+    ssize_t len = recv(socks[1], buf, sizeof(buf), MSG_WAITALL);
+    void *ret;
+    if (pthread_join(writer, &ret) != 0 || ret != NULL) {
+        close_files(2, socks[0], socks[1]);
+        THROW_ERROR("failed to write the second chunk");
+    }
+    if (len != sizeof(buf) || strcmp(buf, ECHO_MSG) != 0) {
+        close_files(2, socks[0], socks[1]);
+        THROW_ERROR("MSG_WAITALL should wait for the whole message");
+    }
+
+    close_files(2, socks[0], socks[1]);
+    return 0;
+}
+
+int test_msg_dontwait() {
+    int socks[2];
+    char buf[16];
+    if (socketpair(AF_UNIX, SOCK_STREAM, 0, socks) < 0) {
+        THROW_ERROR("socketpair failed");
+    }
+
+    if (recv(socks[1], buf, sizeof(buf), MSG_DONTWAIT) != -1 || errno != EAGAIN) {
+        close_files(2, socks[0], socks[1]);
+        THROW_ERROR("recv with MSG_DONTWAIT should not block");
+    }
+    // The socket is still blocking
+    if (fcntl(socks[1], F_GETFL) & O_NONBLOCK) {
+        close_files(2, socks[0], socks[1]);
+        THROW_ERROR("MSG_DONTWAIT should not change the status flags");
+    }
+
+    if (send(socks[0], ECHO_MSG, sizeof(ECHO_MSG), MSG_DONTWAIT) != sizeof(ECHO_MSG) ||
+            recv(socks[1], buf, sizeof(buf), MSG_DONTWAIT) != sizeof(buf)) {
+        close_files(2, socks[0], socks[1]);
+        THROW_ERROR("failed to send and recv with MSG_DONTWAIT");
+    }
+
+    close_files(2, socks[0], socks[1]);
+    return 0;
+}
+
+int test_send_to_closed_peer() {
+    int socks[2];
+    if (socketpair(AF_UNIX, SOCK_STREAM, 0, socks) < 0) {
+        THROW_ERROR("socketpair failed");
+    }
+
+    if (shutdown(socks[0], SHUT_WR) < 0) {
+        close_files(2, socks[0], socks[1]);
+        THROW_ERROR("shutdown failed");
+    }
+    if (send(socks[0], ECHO_MSG, sizeof(ECHO_MSG), MSG_NOSIGNAL) != -1 || errno != EPIPE) {
+        close_files(2, socks[0], socks[1]);
+        THROW_ERROR("send after shutdown should fail with EPIPE");
+    }
+
+    close(socks[0]);
+    if (send(socks[1], ECHO_MSG, sizeof(ECHO_MSG), MSG_NOSIGNAL) != -1 || errno != EPIPE) {
+        close(socks[1]);
+        THROW_ERROR("send to a closed peer should fail with EPIPE");
+    }
+
+    close(socks[1]);
+    return 0;
+}
+
+int test_abstract_addr() {
+    // The name has an embedded null and is not null-terminated
+    const char name[] = "\0my-service\0\xff";
+    struct sockaddr_un addr;
+    memset(&addr, 0, sizeof(struct sockaddr_un));
+    addr.sun_family = AF_UNIX;
+    memcpy(addr.sun_path, name, sizeof(name) - 1);
+    socklen_t addr_len = sizeof(addr.sun_family) + sizeof(name) - 1;
+
+    int listen_fd = socket(AF_UNIX, SOCK_STREAM, 0);
+    if (listen_fd == -1) {
+        THROW_ERROR("failed to create a unix socket");
+    }
+    if (bind(listen_fd, (struct sockaddr *)&addr, addr_len) == -1 ||
+            listen(listen_fd, 5) == -1) {
+        close(listen_fd);
+        THROW_ERROR("failed to listen on an abstract address");
+    }
+    // No file is created for an abstract address
+    if (access("my-service", F_OK) == 0) {
+        close(listen_fd);
+        THROW_ERROR("abstract address should not be in the file system");
+    }
+
+    int client_fd = socket(AF_UNIX, SOCK_STREAM, 0);
+    if (client_fd == -1 || connect(client_fd, (struct sockaddr *)&addr, addr_len) == -1) {
+        close(listen_fd);
+        THROW_ERROR("failed to connect to an abstract address");
+    }
+    int accepted_fd = accept(listen_fd, NULL, NULL);
+    if (accepted_fd == -1) {
+        close_files(2, listen_fd, client_fd);
+        THROW_ERROR("failed to accept");
+    }
+
+    struct sockaddr_un peer_addr;
+    socklen_t peer_addr_len = sizeof(peer_addr);
+    if (getpeername(client_fd, (struct sockaddr *)&peer_addr, &peer_addr_len) == -1 ||
+            peer_addr_len != addr_len ||
+            memcmp(peer_addr.sun_path, name, sizeof(name) - 1) != 0) {
+        close_files(3, listen_fd, client_fd, accepted_fd);
+        THROW_ERROR("the abstract address does not round-trip");
+    }
+
+    close_files(3, listen_fd, client_fd, accepted_fd);
+    return 0;
+}
+
+int test_seqpacket() {
+    // Messages are received one by one over a connection accepted from a listener
+    const char name[] = "\0seqpacket-test";
+    struct sockaddr_un addr;
+    memset(&addr, 0, sizeof(struct sockaddr_un));
+    addr.sun_family = AF_UNIX;
+    memcpy(addr.sun_path, name, sizeof(name) - 1);
+    socklen_t addr_len = sizeof(addr.sun_family) + sizeof(name) - 1;
+
+    int listen_fd = socket(AF_UNIX, SOCK_SEQPACKET, 0);
+    if (listen_fd == -1) {
+        THROW_ERROR("failed to create a seqpacket socket");
+    }
+    if (bind(listen_fd, (struct sockaddr *)&addr, addr_len) == -1 ||
+            listen(listen_fd, 5) == -1) {
+        close(listen_fd);
+        THROW_ERROR("failed to listen on a seqpacket socket");
+    }
+
+    // A socket cannot connect to a listener of another type
+    int stream_fd = socket(AF_UNIX, SOCK_STREAM, 0);
+    if (connect(stream_fd, (struct sockaddr *)&addr, addr_len) != -1 || errno != EPROTOTYPE) {
+        close_files(2, listen_fd, stream_fd);
+        THROW_ERROR("connecting to a listener of another type should fail");
+    }
+    close(stream_fd);
+
+    int client_fd = socket(AF_UNIX, SOCK_SEQPACKET, 0);
+    if (client_fd == -1 || connect(client_fd, (struct sockaddr *)&addr, addr_len) == -1) {
+        close(listen_fd);
+        THROW_ERROR("failed to connect to a seqpacket socket");
+    }
+    int accepted_fd = accept(listen_fd, NULL, NULL);
+    if (accepted_fd == -1) {
+        close_files(2, listen_fd, client_fd);
+        THROW_ERROR("failed to accept");
+    }
+
+    if (send(client_fd, "hello", 5, 0) != 5 || send(client_fd, "world!", 6, 0) != 6) {
+        close_files(3, listen_fd, client_fd, accepted_fd);
+        THROW_ERROR("failed to send messages");
+    }
+
+    char buf[16] = {0};
+    if (recv(accepted_fd, buf, sizeof(buf), 0) != 5 || strncmp(buf, "hello", 5) != 0) {
+        close_files(3, listen_fd, client_fd, accepted_fd);
+        THROW_ERROR("the first message is not received as a whole");
+    }
+
+    // The part of the message that does not fit in the buffer is discarded
+    struct iovec iov = { .iov_base = buf, .iov_len = 3 };
+    struct msghdr msg = { .msg_iov = &iov, .msg_iovlen = 1 };
+    if (recvmsg(accepted_fd, &msg, 0) != 3 || !(msg.msg_flags & MSG_TRUNC) ||
+            strncmp(buf, "wor", 3) != 0) {
+        close_files(3, listen_fd, client_fd, accepted_fd);
+        THROW_ERROR("a truncated message should set MSG_TRUNC");
+    }
+    if (recv(accepted_fd, buf, sizeof(buf), MSG_DONTWAIT) != -1 || errno != EAGAIN) {
+        close_files(3, listen_fd, client_fd, accepted_fd);
+        THROW_ERROR("the rest of a truncated message should be discarded");
+    }
+
+    close_files(3, listen_fd, client_fd, accepted_fd);
+    return 0;
+}
+
+int test_msg_oob() {
+    int socks[2];
+    if (socketpair(AF_UNIX, SOCK_STREAM, 0, socks) < 0) {
+        THROW_ERROR("socketpair failed");
+    }
+
+    // Out-of-band data is not supported by unix socket
+    char buf[16];
+    if (send(socks[0], "a", 1, MSG_OOB) != -1 || errno != EOPNOTSUPP) {
+        close_files(2, socks[0], socks[1]);
+        THROW_ERROR("send with MSG_OOB should fail with EOPNOTSUPP");
+    }
+    if (recv(socks[1], buf, sizeof(buf), MSG_OOB) != -1 || errno != EOPNOTSUPP) {
+        close_files(2, socks[0], socks[1]);
+        THROW_ERROR("recv with MSG_OOB should fail with EOPNOTSUPP");
+    }
+
+    close_files(2, socks[0], socks[1]);
+    return 0;
+}
+
+int test_ioctl_queue_len() {
+    int socks[2];
+    if (socketpair(AF_UNIX, SOCK_STREAM, 0, socks) < 0) {
+        THROW_ERROR("socketpair failed");
+    }
+
+    if (send(socks[0], ECHO_MSG, sizeof(ECHO_MSG), 0) != sizeof(ECHO_MSG)) {
+        close_files(2, socks[0], socks[1]);
+        THROW_ERROR("send failed");
+    }
+
+    int outq = -1;
+    int inq = -1;
+    if (ioctl(socks[0], SIOCOUTQ, &outq) < 0 || outq != sizeof(ECHO_MSG) ||
+            ioctl(socks[1], SIOCINQ, &inq) < 0 || inq != sizeof(ECHO_MSG)) {
+        close_files(2, socks[0], socks[1]);
+        THROW_ERROR("the queue lengths should be the size of the unread data");
+    }
+
+    char buf[sizeof(ECHO_MSG)];
+    if (recv(socks[1], buf, sizeof(buf), 0) != sizeof(ECHO_MSG) ||
+            ioctl(socks[0], SIOCOUTQ, &outq) < 0 || outq != 0) {
+        close_files(2, socks[0], socks[1]);
+        THROW_ERROR("the output queue should be empty after the data is read");
+    }
+
+    close_files(2, socks[0], socks[1]);
+    return 0;
+}
+
+int test_backlog_and_emfile() {
+    const char name[] = "\0backlog-test";
+    struct sockaddr_un addr;
+    memset(&addr, 0, sizeof(struct sockaddr_un));
+    addr.sun_family = AF_UNIX;
+    memcpy(addr.sun_path, name, sizeof(name) - 1);
+    socklen_t addr_len = sizeof(addr.sun_family) + sizeof(name) - 1;
+
+    int listen_fd = socket(AF_UNIX, SOCK_STREAM, 0);
+    if (listen_fd == -1) {
+        THROW_ERROR("failed to create a unix socket");
+    }
+    if (bind(listen_fd, (struct sockaddr *)&addr, addr_len) == -1 ||
+            listen(listen_fd, 1) == -1) {
+        close(listen_fd);
+        THROW_ERROR("failed to listen");
+    }
+
+    // Like Linux, one more connection than the backlog is queued
+    int client_fds[3];
+    for (int i = 0; i < 3; i++) {
+        client_fds[i] = socket(AF_UNIX, SOCK_STREAM | SOCK_NONBLOCK, 0);
+        if (client_fds[i] == -1) {
+            THROW_ERROR("failed to create a unix socket");
+        }
+    }
+    if (connect(client_fds[0], (struct sockaddr *)&addr, addr_len) == -1 ||
+            connect(client_fds[1], (struct sockaddr *)&addr, addr_len) == -1) {
+        close_files(4, listen_fd, client_fds[0], client_fds[1], client_fds[2]);
+        THROW_ERROR("failed to connect");
+    }
+    if (connect(client_fds[2], (struct sockaddr *)&addr, addr_len) != -1 || errno != EAGAIN) {
+        close_files(4, listen_fd, client_fds[0], client_fds[1], client_fds[2]);
+        THROW_ERROR("connecting to a full backlog should fail with EAGAIN");
+    }
+
+    // Accepting fails when no fd is available, and the connection is kept
+    struct rlimit old_limit;
+    struct rlimit new_limit;
+    int free_fd = dup(listen_fd);
+    close(free_fd);
+    if (getrlimit(RLIMIT_NOFILE, &old_limit) == -1) {
+        close_files(4, listen_fd, client_fds[0], client_fds[1], client_fds[2]);
+        THROW_ERROR("failed to get the limit of open files");
+    }
+    new_limit = old_limit;
+    new_limit.rlim_cur = free_fd;
+    if (setrlimit(RLIMIT_NOFILE, &new_limit) == -1) {
+        close_files(4, listen_fd, client_fds[0], client_fds[1], client_fds[2]);
+        THROW_ERROR("failed to set the limit of open files");
+    }
+    int accepted_fd = accept(listen_fd, NULL, NULL);
+    int accept_errno = errno;
+    setrlimit(RLIMIT_NOFILE, &old_limit);
+    if (accepted_fd != -1 || accept_errno != EMFILE) {
+        close_files(4, listen_fd, client_fds[0], client_fds[1], client_fds[2]);
+        THROW_ERROR("accepting without a free fd should fail with EMFILE");
+    }
+
+    for (int i = 0; i < 2; i++) {
+        accepted_fd = accept(listen_fd, NULL, NULL);
+        if (accepted_fd == -1) {
+            close_files(4, listen_fd, client_fds[0], client_fds[1], client_fds[2]);
+            THROW_ERROR("the queued connections should be accepted");
+        }
+        close(accepted_fd);
+    }
+
+    close_files(4, listen_fd, client_fds[0], client_fds[1], client_fds[2]);
+    return 0;
+}
+
+static int check_socket_info(int fd, int type, int domain, int protocol, int acceptconn) {
+    int optnames[4] = {SO_TYPE, SO_DOMAIN, SO_PROTOCOL, SO_ACCEPTCONN};
+    int expected[4] = {type, domain, protocol, acceptconn};
+    for (int i = 0; i < 4; i++) {
+        int val = -1;
+        socklen_t optlen = sizeof(val);
+        if (getsockopt(fd, SOL_SOCKET, optnames[i], &val, &optlen) < 0 ||
+                optlen != sizeof(val) || val != expected[i]) {
+            THROW_ERROR("the socket info of option %d is wrong", optnames[i]);
+        }
+    }
+    return 0;
+}
+
+int test_socket_info() {
+    int stream_fd = socket(AF_UNIX, SOCK_STREAM, 0);
+    int seqpacket_fd = socket(AF_UNIX, SOCK_SEQPACKET, 0);
+    if (stream_fd < 0 || seqpacket_fd < 0) {
+        close_files(2, stream_fd, seqpacket_fd);
+        THROW_ERROR("failed to create unix sockets");
+    }
+    if (check_socket_info(stream_fd, SOCK_STREAM, AF_UNIX, 0, 0) < 0 ||
+            check_socket_info(seqpacket_fd, SOCK_SEQPACKET, AF_UNIX, 0, 0) < 0) {
+        close_files(2, stream_fd, seqpacket_fd);
+        THROW_ERROR("wrong info of unix sockets");
+    }
+
+    const char name[] = "\0socket-info-test";
+    struct sockaddr_un addr;
+    memset(&addr, 0, sizeof(struct sockaddr_un));
+    addr.sun_family = AF_UNIX;
+    memcpy(addr.sun_path, name, sizeof(name) - 1);
+    socklen_t addr_len = sizeof(addr.sun_family) + sizeof(name) - 1;
+    if (bind(stream_fd, (struct sockaddr *)&addr, addr_len) < 0 ||
+            listen(stream_fd, 5) < 0 ||
+            check_socket_info(stream_fd, SOCK_STREAM, AF_UNIX, 0, 1) < 0) {
+        close_files(2, stream_fd, seqpacket_fd);
+        THROW_ERROR("a listening socket should accept connections");
+    }
+
+    int type = SOCK_DGRAM;
+    if (setsockopt(stream_fd, SOL_SOCKET, SO_TYPE, &type, sizeof(type)) != -1 ||
+            errno != ENOPROTOOPT) {
+        close_files(2, stream_fd, seqpacket_fd);
+        THROW_ERROR("SO_TYPE should be read-only");
+    }
+
+    close_files(2, stream_fd, seqpacket_fd);
+    return 0;
+}
+
+static int unix_stream_pair(int fds[2]) {
+    return socketpair(AF_UNIX, SOCK_STREAM, 0, fds);
+}
+
+static int unix_seqpacket_pair(int fds[2]) {
+    return socketpair(AF_UNIX, SOCK_SEQPACKET, 0, fds);
+}
+
+int test_stream_pair_checks() {
+    static const stream_pair_func_t make_pairs[] = {
+        unix_stream_pair,
+        unix_seqpacket_pair,
+    };
+    for (int pi = 0; pi < ARRAY_SIZE(make_pairs); pi++) {
+        if (run_stream_pair_checks(make_pairs[pi]) < 0) {
+            THROW_ERROR("the checks failed for the socket type %d", pi);
+        }
+    }
+    return 0;
+}
+
+int test_accept_peer_addr() {
+    // Abstract addresses start with a null byte
+    struct sockaddr_un listen_addr = { .sun_family = AF_UNIX };
+    strcpy(listen_addr.sun_path + 1, "accept_peer_addr_listen");
+    socklen_t listen_addr_len = sizeof(sa_family_t) + 1 + strlen(listen_addr.sun_path + 1);
+    struct sockaddr_un client_addr = { .sun_family = AF_UNIX };
+    strcpy(client_addr.sun_path + 1, "accept_peer_addr_client");
+    socklen_t client_addr_len = sizeof(sa_family_t) + 1 + strlen(client_addr.sun_path + 1);
+
+    int listen_fd = socket(AF_UNIX, SOCK_STREAM, 0);
+    if (listen_fd < 0 ||
+            bind(listen_fd, (struct sockaddr *)&listen_addr, listen_addr_len) < 0 ||
+            listen(listen_fd, 5) < 0) {
+        THROW_ERROR("failed to listen");
+    }
+
+    // The address of an unbound client has only the address family
+    int unbound_fd = socket(AF_UNIX, SOCK_STREAM, 0);
+    if (unbound_fd < 0 ||
+            connect(unbound_fd, (struct sockaddr *)&listen_addr, listen_addr_len) < 0) {
+        close(listen_fd);
+        THROW_ERROR("failed to connect");
+    }
+    struct sockaddr_un addr;
+    memset(&addr, 0, sizeof(addr));
+    socklen_t addr_len = sizeof(addr);
+    int accepted_fd = accept(listen_fd, (struct sockaddr *)&addr, &addr_len);
+    if (accepted_fd < 0) {
+        close_files(2, listen_fd, unbound_fd);
+        THROW_ERROR("failed to accept");
+    }
+    close_files(2, unbound_fd, accepted_fd);
+    if (addr_len != sizeof(sa_family_t) || addr.sun_family != AF_UNIX) {
+        close(listen_fd);
+        THROW_ERROR("the address of an unbound peer should be unnamed");
+    }
+
+    // The address of a bound client is returned
+    int bound_fd = socket(AF_UNIX, SOCK_STREAM, 0);
+    if (bound_fd < 0 ||
+            bind(bound_fd, (struct sockaddr *)&client_addr, client_addr_len) < 0 ||
+            connect(bound_fd, (struct sockaddr *)&listen_addr, listen_addr_len) < 0) {
+        close(listen_fd);
+        THROW_ERROR("failed to connect from a bound socket");
+    }
+    memset(&addr, 0, sizeof(addr));
+    addr_len = sizeof(addr);
+    accepted_fd = accept(listen_fd, (struct sockaddr *)&addr, &addr_len);
+    if (accepted_fd < 0) {
+        close_files(2, listen_fd, bound_fd);
+        THROW_ERROR("failed to accept");
+    }
+    close_files(3, listen_fd, bound_fd, accepted_fd);
+    if (addr_len != client_addr_len || memcmp(&addr, &client_addr, client_addr_len) != 0) {
+        THROW_ERROR("the address of a bound peer is wrong");
+    }
+
+    return 0;
+}
+
+// Unlike TCP, the close of the peer hangs up a unix socket at once
+int test_poll_hup_on_peer_close() {
+    int socks[2];
+    if (socketpair(AF_UNIX, SOCK_STREAM, 0, socks) < 0) {
+        THROW_ERROR("socketpair failed");
+    }
+
+    close(socks[1]);
+    struct pollfd pfd = { .fd = socks[0], .events = POLLIN | POLLOUT | POLLRDHUP };
+    if (poll(&pfd, 1, 0) != 1 || !(pfd.revents & POLLHUP) || !(pfd.revents & POLLRDHUP)) {
+        close(socks[0]);
+        THROW_ERROR("both HUP and RDHUP are expected after the peer is closed");
+    }
+
+    close(socks[0]);
+    return 0;
+}
+
+int test_socketpair_flags() {
+    int socks[2];
+    if (socketpair(AF_UNIX, SOCK_STREAM | SOCK_NONBLOCK | SOCK_CLOEXEC, 0, socks) < 0) {
+        THROW_ERROR("socketpair failed");
+    }
+
+    // Both ends carry the requested flags
+    for (int i = 0; i < 2; i++) {
+        if (!(fcntl(socks[i], F_GETFL) & O_NONBLOCK) ||
+                !(fcntl(socks[i], F_GETFD) & FD_CLOEXEC)) {
+            close_files(2, socks[0], socks[1]);
+            THROW_ERROR("the end should be nonblocking and close-on-exec");
+        }
+    }
+    close_files(2, socks[0], socks[1]);
+
+    // Neither end carries the flags if not requested
+    if (socketpair(AF_UNIX, SOCK_STREAM, 0, socks) < 0) {
+        THROW_ERROR("socketpair failed");
+    }
+    for (int i = 0; i < 2; i++) {
+        if ((fcntl(socks[i], F_GETFL) & O_NONBLOCK) ||
+                (fcntl(socks[i], F_GETFD) & FD_CLOEXEC)) {
+            close_files(2, socks[0], socks[1]);
+            THROW_ERROR("the end should be neither nonblocking nor close-on-exec");
+        }
+    }
+
+    close_files(2, socks[0], socks[1]);
+    return 0;
+}
+
+static void handle_sigusr1(int signum) {
+}
+
+static volatile int read_errno = 0;
+
+static void *read_routine(void *arg) {
+    int fd = *(int *)arg;
+    char buf[16];
+    if (read(fd, buf, sizeof(buf)) < 0) {
+        read_errno = errno;
+    } else {
+        read_errno = -1;
+    }
+    return NULL;
+}
+
+int test_read_interrupted_by_signal() {
+    int socks[2];
+    if (socketpair(AF_UNIX, SOCK_STREAM, 0, socks) < 0) {
+        THROW_ERROR("socketpair failed");
+    }
+
+    struct sigaction new_action = {0}, old_action;
+    new_action.sa_handler = handle_sigusr1;
+    if (sigaction(SIGUSR1, &new_action, &old_action) < 0) {
+        close_files(2, socks[0], socks[1]);
+        THROW_ERROR("failed to set the signal handler");
+    }
+
+    pthread_t tid;
+    read_errno = 0;
+    if (pthread_create(&tid, NULL, read_routine, &socks[0])) {
+        close_files(2, socks[0], socks[1]);
+        THROW_ERROR("failed to create the thread");
+    }
+    // Keep signaling in case the signal arrives before the thread is blocked
+    for (int i = 0; i < 50 && read_errno == 0; i++) {
+        pthread_kill(tid, SIGUSR1);
+        usleep(100 * 1000);
+    }
+    if (read_errno == 0) {
+        // Unblock the thread so that it can be joined
+        write(socks[1], ECHO_MSG, sizeof(ECHO_MSG));
+    }
+    pthread_join(tid, NULL);
+    sigaction(SIGUSR1, &old_action, NULL);
+    close_files(2, socks[0], socks[1]);
+
+    if (read_errno != EINTR) {
+        THROW_ERROR("the blocked read should be interrupted with EINTR");
+    }
+    return 0;
+}
+
+int test_poll_zero_timeout() {
+    int socks[2];
+    if (socketpair(AF_UNIX, SOCK_STREAM, 0, socks) < 0) {
+        THROW_ERROR("socketpair failed");
+    }
+
+    // A negative fd is ignored
+    struct pollfd pfds[2] = {
+        { .fd = socks[0], .events = POLLIN },
+        { .fd = -1, .events = POLLIN },
+    };
+    if (poll(pfds, 2, 0) != 0 || pfds[0].revents != 0 || pfds[1].revents != 0) {
+        close_files(2, socks[0], socks[1]);
+        THROW_ERROR("no socket should be ready");
+    }
+
+    if (write(socks[1], ECHO_MSG, sizeof(ECHO_MSG)) != sizeof(ECHO_MSG)) {
+        close_files(2, socks[0], socks[1]);
+        THROW_ERROR("failed to write");
+    }
+    if (poll(pfds, 2, 0) != 1 || pfds[0].revents != POLLIN || pfds[1].revents != 0) {
+        close_files(2, socks[0], socks[1]);
+        THROW_ERROR("the socket should be readable");
+    }
+
+    close_files(2, socks[0], socks[1]);
+    return 0;
+}
+
+static int send_with_credentials(int fd, struct ucred *cred) {
+    char data = 'x';
+    char control[CMSG_SPACE(sizeof(struct ucred))] = {0};
+    struct iovec iov = {.iov_base = &data, .iov_len = 1};
+    struct msghdr msg = {
+        .msg_iov = &iov,
+        .msg_iovlen = 1,
+        .msg_control = control,
+        .msg_controllen = sizeof(control),
+    };
+    struct cmsghdr *cmsg = CMSG_FIRSTHDR(&msg);
+    cmsg->cmsg_level = SOL_SOCKET;
+    cmsg->cmsg_type = SCM_CREDENTIALS;
+    cmsg->cmsg_len = CMSG_LEN(sizeof(struct ucred));
+    memcpy(CMSG_DATA(cmsg), cred, sizeof(struct ucred));
+    return sendmsg(fd, &msg, 0);
+}
+
+int test_scm_credentials() {
+    int socks[2];
+    int enable = 1;
+    if (socketpair(AF_UNIX, SOCK_SEQPACKET, 0, socks) < 0) {
+        THROW_ERROR("socketpair failed");
+    }
+    if (setsockopt(socks[1], SOL_SOCKET, SO_PASSCRED, &enable, sizeof(enable)) < 0) {
+        close_files(2, socks[0], socks[1]);
+        THROW_ERROR("setsockopt SO_PASSCRED failed");
+    }
+
+    // Forged credentials are rejected
+    struct ucred cred = {.pid = getpid() + 1, .uid = getuid(), .gid = getgid()};
+    if (send_with_credentials(socks[0], &cred) >= 0 || errno != EPERM) {
+        close_files(2, socks[0], socks[1]);
+        THROW_ERROR("sending forged credentials should fail with EPERM");
+    }
+    cred.pid = getpid();
+    if (send_with_credentials(socks[0], &cred) != 1) {
+        close_files(2, socks[0], socks[1]);
+        THROW_ERROR("failed to send the credentials of the sender");
+    }
+    // The credentials are received even if the sender does not give them
+    if (write(socks[0], "y", 1) != 1) {
+        close_files(2, socks[0], socks[1]);
+        THROW_ERROR("failed to write");
+    }
+
+    for (int i = 0; i < 2; i++) {
+        char buf[8];
+        char control[CMSG_SPACE(sizeof(struct ucred))] = {0};
+        struct iovec iov = {.iov_base = buf, .iov_len = sizeof(buf)};
+        struct msghdr msg = {
+            .msg_iov = &iov,
+            .msg_iovlen = 1,
+            .msg_control = control,
+            .msg_controllen = sizeof(control),
+        };
+        if (recvmsg(socks[1], &msg, 0) != 1 || (msg.msg_flags & MSG_CTRUNC)) {
+            close_files(2, socks[0], socks[1]);
+            THROW_ERROR("recvmsg failed");
+        }
+
+        struct cmsghdr *cmsg = CMSG_FIRSTHDR(&msg);
+        if (cmsg == NULL || cmsg->cmsg_level != SOL_SOCKET ||
+                cmsg->cmsg_type != SCM_CREDENTIALS ||
+                cmsg->cmsg_len != CMSG_LEN(sizeof(struct ucred))) {
+            close_files(2, socks[0], socks[1]);
+            THROW_ERROR("no SCM_CREDENTIALS control message received");
+        }
+        struct ucred recv_cred;
+        memcpy(&recv_cred, CMSG_DATA(cmsg), sizeof(recv_cred));
+        if (recv_cred.pid != getpid() || recv_cred.uid != getuid() ||
+                recv_cred.gid != getgid()) {
+            close_files(2, socks[0], socks[1]);
+            THROW_ERROR("received credentials mismatch");
+        }
+    }
+
+    close_files(2, socks[0], socks[1]);
+    return 0;
+}
+
+// The data of a stream sent in a row by the same process is received together
+// with a single SCM_CREDENTIALS
+int test_scm_credentials_stream() {
+    int socks[2];
+    int enable = 1;
+    if (socketpair(AF_UNIX, SOCK_STREAM, 0, socks) < 0) {
+        THROW_ERROR("socketpair failed");
+    }
+    if (setsockopt(socks[1], SOL_SOCKET, SO_PASSCRED, &enable, sizeof(enable)) < 0) {
+        close_files(2, socks[0], socks[1]);
+        THROW_ERROR("setsockopt SO_PASSCRED failed");
+    }
+    if (write(socks[0], "a", 1) != 1 || write(socks[0], "b", 1) != 1 ||
+            shutdown(socks[0], SHUT_WR) < 0) {
+        close_files(2, socks[0], socks[1]);
+        THROW_ERROR("failed to write");
+    }
+
+    int expected_lens[2] = {2, 0};
+    for (int i = 0; i < 2; i++) {
+        char buf[8];
+        char control[2 * CMSG_SPACE(sizeof(struct ucred))] = {0};
+        struct iovec iov = {.iov_base = buf, .iov_len = sizeof(buf)};
+        struct msghdr msg = {
+            .msg_iov = &iov,
+            .msg_iovlen = 1,
+            .msg_control = control,
+            .msg_controllen = sizeof(control),
+        };
+        if (recvmsg(socks[1], &msg, 0) != expected_lens[i]) {
+            close_files(2, socks[0], socks[1]);
+            THROW_ERROR("recvmsg failed");
+        }
+
+        // Linux gives the credentials of no process at the EOF, while Occlum
+        // gives none
+        int num_creds = 0;
+        struct ucred recv_cred = {0};
+        for (struct cmsghdr *cmsg = CMSG_FIRSTHDR(&msg); cmsg != NULL;
+                cmsg = CMSG_NXTHDR(&msg, cmsg)) {
+            if (cmsg->cmsg_level == SOL_SOCKET && cmsg->cmsg_type == SCM_CREDENTIALS) {
+                memcpy(&recv_cred, CMSG_DATA(cmsg), sizeof(recv_cred));
+                num_creds++;
+            }
+        }
+        int expected_pid = expected_lens[i] > 0 ? getpid() : 0;
+        if (num_creds > 1 || (expected_lens[i] > 0 && num_creds != 1) ||
+                (num_creds == 1 && recv_cred.pid != expected_pid)) {
+            close_files(2, socks[0], socks[1]);
+            THROW_ERROR("unexpected SCM_CREDENTIALS received");
+        }
+    }
+
+    close_files(2, socks[0], socks[1]);
+    return 0;
+}
+
+int test_hup_on_peer_process_exit() {
+    int socks[2];
+    if (socketpair(AF_UNIX, SOCK_STREAM, 0, socks) < 0) {
+        THROW_ERROR("socketpair failed");
+    }
+
+    // The child process owns the only reference to socks[0]
+    const char *child_prog = "/bin/hello_world";
+    const char *child_argv[2] = {child_prog, NULL};
+    int child_pid;
+    posix_spawn_file_actions_t file_actions;
+    posix_spawn_file_actions_init(&file_actions);
+    posix_spawn_file_actions_adddup2(&file_actions, socks[0], STDOUT_FILENO);
+    posix_spawn_file_actions_addclose(&file_actions, socks[0]);
+    posix_spawn_file_actions_addclose(&file_actions, socks[1]);
+    if (posix_spawn(&child_pid, child_prog, &file_actions,
+                    NULL, (char *const *)child_argv, NULL) < 0) {
+        close_files(2, socks[0], socks[1]);
+        THROW_ERROR("failed to spawn a child process");
+    }
+    close(socks[0]);
+
+    // The exit of the peer process wakes up the poller with POLLHUP, after
+    // the data written by the peer is read
+    struct pollfd pfd = {.fd = socks[1], .events = POLLIN};
+    char buf[256];
+    while (1) {
+        if (poll(&pfd, 1, 5000) != 1) {
+            close(socks[1]);
+            THROW_ERROR("the exit of the peer process is not detected");
+        }
+        if (pfd.revents & POLLHUP) {
+            break;
+        }
+        if (read(socks[1], buf, sizeof(buf)) <= 0) {
+            close(socks[1]);
+            THROW_ERROR("failed to read the data written by the peer");
+        }
+    }
+    while (read(socks[1], buf, sizeof(buf)) > 0) {
+    }
+
+    int status = 0;
+    if (wait4(child_pid, &status, 0, NULL) < 0) {
+        close(socks[1]);
+        THROW_ERROR("failed to wait4 the child process");
+    }
+    close(socks[1]);
+    return 0;
+}
+
+int test_resize_buf_of_connected_socket() {
+    int socks[2];
+    static char data[64 * 1024];
+    static char buf[64 * 1024];
+    for (int i = 0; i < sizeof(data); i++) {
+        data[i] = (char)i;
+    }
+    if (socketpair(AF_UNIX, SOCK_STREAM, 0, socks) < 0) {
+        THROW_ERROR("socketpair failed");
+    }
+
+    int size = 8192, actual = 0;
+    socklen_t optlen = sizeof(actual);
+    if (setsockopt(socks[0], SOL_SOCKET, SO_SNDBUF, &size, sizeof(size)) < 0 ||
+            getsockopt(socks[0], SOL_SOCKET, SO_SNDBUF, &actual, &optlen) < 0) {
+        close_files(2, socks[0], socks[1]);
+        THROW_ERROR("failed to set SO_SNDBUF");
+    }
+
+    // The writer is blocked once the buffer is full
+    if (send(socks[0], data, sizeof(data), MSG_DONTWAIT) != actual ||
+            send(socks[0], data, 1, MSG_DONTWAIT) != -1 || errno != EAGAIN) {
+        close_files(2, socks[0], socks[1]);
+        THROW_ERROR("the buffered data should be limited by SO_SNDBUF");
+    }
+    struct pollfd pfd = {.fd = socks[0], .events = POLLOUT};
+    if (poll(&pfd, 1, 0) != 0) {
+        close_files(2, socks[0], socks[1]);
+        THROW_ERROR("the socket should not be writable");
+    }
+
+    // Growing the buffer keeps the buffered data and makes room for more
+    int old_actual = actual;
+    size = 16384;
+    if (setsockopt(socks[0], SOL_SOCKET, SO_SNDBUF, &size, sizeof(size)) < 0 ||
+            getsockopt(socks[0], SOL_SOCKET, SO_SNDBUF, &actual, &optlen) < 0 ||
+            actual <= old_actual) {
+        close_files(2, socks[0], socks[1]);
+        THROW_ERROR("failed to grow SO_SNDBUF");
+    }
+    if (poll(&pfd, 1, 0) != 1 || !(pfd.revents & POLLOUT)) {
+        close_files(2, socks[0], socks[1]);
+        THROW_ERROR("the socket should be writable");
+    }
+    if (send(socks[0], data + old_actual, sizeof(data) - old_actual, MSG_DONTWAIT) !=
+            actual - old_actual) {
+        close_files(2, socks[0], socks[1]);
+        THROW_ERROR("the buffered data should be limited by the new SO_SNDBUF");
+    }
+
+    // Shrinking the buffer below the buffered data keeps the data, too
+    size = 0;
+    if (setsockopt(socks[0], SOL_SOCKET, SO_SNDBUF, &size, sizeof(size)) < 0) {
+        close_files(2, socks[0], socks[1]);
+        THROW_ERROR("failed to shrink SO_SNDBUF");
+    }
+
+    int len = 0;
+    while (len < actual) {
+        int ret = recv(socks[1], buf + len, sizeof(buf) - len, MSG_DONTWAIT);
+        if (ret <= 0) {
+            close_files(2, socks[0], socks[1]);
+            THROW_ERROR("failed to receive the buffered data");
+        }
+        len += ret;
+    }
+    if (len != actual || memcmp(buf, data, len) != 0) {
+        close_files(2, socks[0], socks[1]);
+        THROW_ERROR("the buffered data mismatch");
+    }
+
+    close_files(2, socks[0], socks[1]);
+    return 0;
+}
+
+int test_scm_rights_boundary() {
+    int socks[2], pipe_fds[2];
+    char buf[32] = {0};
+    if (socketpair(AF_UNIX, SOCK_STREAM, 0, socks) < 0) {
+        THROW_ERROR("socketpair failed");
+    }
+    if (pipe(pipe_fds) < 0) {
+        THROW_ERROR("pipe failed");
+    }
+
+    // Send the write end of the pipe with "cd", between "ab" and "ef"
+    char send_control[CMSG_SPACE(sizeof(int))] = {0};
+    struct iovec send_iov = {.iov_base = "cd", .iov_len = 2};
+    struct msghdr send_msg = {
+        .msg_iov = &send_iov,
+        .msg_iovlen = 1,
+        .msg_control = send_control,
+        .msg_controllen = sizeof(send_control),
+    };
+    struct cmsghdr *cmsg = CMSG_FIRSTHDR(&send_msg);
+    cmsg->cmsg_level = SOL_SOCKET;
+    cmsg->cmsg_type = SCM_RIGHTS;
+    cmsg->cmsg_len = CMSG_LEN(sizeof(int));
+    memcpy(CMSG_DATA(cmsg), &pipe_fds[1], sizeof(int));
+    if (write(socks[0], "ab", 2) != 2 || sendmsg(socks[0], &send_msg, 0) != 2 ||
+            write(socks[0], "ef", 2) != 2) {
+        THROW_ERROR("failed to send");
+    }
+    close(pipe_fds[1]);
+
+    // Like Linux, the receive stops at the end of the data sent with the fd
+    char recv_control[CMSG_SPACE(sizeof(int))] = {0};
+    struct iovec recv_iov = {.iov_base = buf, .iov_len = sizeof(buf)};
+    struct msghdr recv_msg = {
+        .msg_iov = &recv_iov,
+        .msg_iovlen = 1,
+        .msg_control = recv_control,
+        .msg_controllen = sizeof(recv_control),
+    };
+    if (recvmsg(socks[1], &recv_msg, 0) != 4 || strncmp(buf, "abcd", 4) != 0) {
+        THROW_ERROR("the data sent after the fd should not be received with it");
+    }
+    cmsg = CMSG_FIRSTHDR(&recv_msg);
+    if (cmsg == NULL || cmsg->cmsg_level != SOL_SOCKET || cmsg->cmsg_type != SCM_RIGHTS) {
+        THROW_ERROR("no SCM_RIGHTS control message received");
+    }
+    int recv_fd;
+    memcpy(&recv_fd, CMSG_DATA(cmsg), sizeof(int));
+
+    recv_msg.msg_controllen = sizeof(recv_control);
+    if (recvmsg(socks[1], &recv_msg, 0) != 2 || strncmp(buf, "ef", 2) != 0) {
+        THROW_ERROR("failed to receive the data sent after the fd");
+    }
+    if (recv_msg.msg_controllen != 0) {
+        THROW_ERROR("the fd should be received only once");
+    }
+
+    close_files(4, socks[0], socks[1], pipe_fds[0], recv_fd);
+    return 0;
+}
+
+int test_lseek() {
+    int socks[2];
+    if (socketpair(AF_UNIX, SOCK_STREAM, 0, socks) < 0) {
+        THROW_ERROR("socketpair failed");
+    }
+
+    if (lseek(socks[0], 0, SEEK_CUR) != -1 || errno != ESPIPE) {
+        THROW_ERROR("lseek on socket should fail with ESPIPE");
+    }
+
+    close_files(2, socks[0], socks[1]);
+    return 0;
+}
+
 static test_case_t test_cases[] = {
     TEST_CASE(test_unix_socket_inter_process),
     TEST_CASE(test_socketpair_inter_process),
@@ -568,6 +1816,34 @@ static test_case_t test_cases[] = {
     TEST_CASE(test_unix_socket_rename),
     TEST_CASE(test_epoll_wait),
     TEST_CASE(test_sendmsg_recvmsg),
+    TEST_CASE(test_recv_peek),
+    TEST_CASE(test_scm_rights),
+    TEST_CASE(test_scm_rights_boundary),
+    TEST_CASE(test_peercred),
+    TEST_CASE(test_epoll_rdhup),
+    TEST_CASE(test_accept4_flags),
+    TEST_CASE(test_recv_timeout),
+    TEST_CASE(test_lseek),
+    TEST_CASE(test_ioctl_queue_len),
+    TEST_CASE(test_backlog_and_emfile),
+    TEST_CASE(test_socket_info),
+    TEST_CASE(test_stream_pair_checks),
+    TEST_CASE(test_accept_peer_addr),
+    TEST_CASE(test_poll_hup_on_peer_close),
+    TEST_CASE(test_socketpair_flags),
+    TEST_CASE(test_read_interrupted_by_signal),
+    TEST_CASE(test_poll_zero_timeout),
+    TEST_CASE(test_scm_credentials),
+    TEST_CASE(test_scm_credentials_stream),
+    TEST_CASE(test_hup_on_peer_process_exit),
+    TEST_CASE(test_resize_buf_of_connected_socket),
+    TEST_CASE(test_msg_oob),
+    TEST_CASE(test_seqpacket),
+    TEST_CASE(test_abstract_addr),
+    TEST_CASE(test_send_to_closed_peer),
+    TEST_CASE(test_msg_dontwait),
+    TEST_CASE(test_recv_waitall),
+    TEST_CASE(test_sndbuf),
 };
 
 int main(int argc, const char *argv[]) {
@@ -286,6 +286,7 @@ fn main() {
                 default_mmap_size: occlum_config.process.default_mmap_size,
             },
             env: occlum_config.env,
+            net: occlum_config.net,
             app: app_config,
         };
 
@@ -478,6 +479,8 @@ struct OcclumConfiguration {
     process: OcclumProcess,
     entry_points: serde_json::Value,
     env: serde_json::Value,
+    #[serde(default)]
+    net: OcclumNet,
     metadata: OcclumMetadata,
     mount: Vec<OcclumMount>,
 }
@@ -501,6 +504,26 @@ struct OcclumProcess {
     default_mmap_size: String,
 }
 
+#[derive(Debug, PartialEq, Clone, Deserialize, Serialize)]
+struct OcclumNet {
+    #[serde(default = "OcclumNet::get_sockopt_pass_through")]
+    sockopt_pass_through: bool,
+}
+
+impl OcclumNet {
+    fn get_sockopt_pass_through() -> bool {
+        true
+    }
+}
+
+impl Default for OcclumNet {
+    fn default() -> OcclumNet {
+        OcclumNet {
+            sockopt_pass_through: OcclumNet::get_sockopt_pass_through(),
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Deserialize)]
 struct OcclumMetaID {
     high: String,
@@ -595,5 +618,6 @@ struct InternalOcclumJson {
     resource_limits: InternalResourceLimits,
     process: OcclumProcess,
     env: serde_json::Value,
+    net: OcclumNet,
     app: serde_json::Value,
 }